@@ -0,0 +1,87 @@
+use crate::grid::{Field, Grid};
+use crate::Solution;
+
+/// Twice the signed area of the polygon through the loop tiles, by the shoelace formula.
+pub fn shoelace_area_doubled(l: &[Field]) -> i64 {
+    (0..l.len())
+        .map(|i| {
+            let (y1, x1) = l[i].coordinate;
+            let (y2, x2) = l[(i + 1) % l.len()].coordinate;
+            x1 as i64 * y2 as i64 - x2 as i64 * y1 as i64
+        })
+        .sum()
+}
+
+/// Tiles inside the loop by Pick's theorem, `A = i + b / 2 - 1`, where every loop tile is a
+/// boundary point.
+pub fn count_enclosed_pick(l: &[Field]) -> Solution {
+    let area_doubled = shoelace_area_doubled(l).abs();
+    ((area_doubled - l.len() as i64) / 2 + 1) as Solution
+}
+
+/// Tiles inside the loop by the even-odd rule. Scanning a row left to right, the inside
+/// flips at every loop tile that connects north, so `|`, `L` and `J`. `F7` and `LJ` pairs
+/// then flip twice and `FJ` and `L7` once, as they should.
+pub fn enclosed_tiles_scanline(grid: &Grid, l: &[Field]) -> Vec<(usize, usize)> {
+    let (height, width) = grid.get_size();
+    let mut on_loop = vec![vec![false; width]; height];
+    let mut connects_north = vec![vec![false; width]; height];
+    for (i, field) in l.iter().enumerate() {
+        let (y, x) = field.coordinate;
+        on_loop[y][x] = true;
+        let previous = l[(i + l.len() - 1) % l.len()].coordinate;
+        let next = l[(i + 1) % l.len()].coordinate;
+        connects_north[y][x] = y > 0 && (previous == (y - 1, x) || next == (y - 1, x));
+    }
+    let mut enclosed = Vec::new();
+    for y in 0..height {
+        let mut inside = false;
+        for x in 0..width {
+            if on_loop[y][x] {
+                inside ^= connects_north[y][x];
+            } else if inside {
+                enclosed.push((y, x));
+            }
+        }
+    }
+    enclosed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fastest_path::get_loop;
+    use crate::{parse, TEST_INPUT, TEST_INPUT_2};
+
+    #[test]
+    pub fn test_both_agree() {
+        for (input, expected) in [(TEST_INPUT, 1), (TEST_INPUT_2, 10)] {
+            let mut parse_output = parse(input);
+            let l = get_loop(&mut parse_output);
+            let scanline = enclosed_tiles_scanline(&parse_output.0, &l);
+            assert_eq!(count_enclosed_pick(&l), expected);
+            assert_eq!(scanline.len(), expected as usize);
+        }
+    }
+
+    #[test]
+    pub fn test_squeezed_between_pipes() {
+        let input = "..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........";
+        let mut parse_output = parse(input);
+        let l = get_loop(&mut parse_output);
+        assert_eq!((shoelace_area_doubled(&l).abs(), l.len()), (50, 44));
+        assert_eq!(count_enclosed_pick(&l), 4);
+        assert_eq!(
+            enclosed_tiles_scanline(&parse_output.0, &l),
+            vec![(6, 2), (6, 3), (6, 6), (6, 7)]
+        );
+    }
+}
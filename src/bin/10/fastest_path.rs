@@ -48,8 +48,8 @@ pub fn get_loop(out: &mut ParseOutput) -> Vec<Field> {
             l[0].coordinate.0 as Solution - prev.coordinate.0 as Solution,
             l[0].coordinate.1 as Solution - prev.coordinate.1 as Solution,
         ) {
-            (0, -1) => West,
-            (0, 1) => East,
+            (0, -1) => East,
+            (0, 1) => West,
             (-1, 0) => South,
             (1, 0) => North,
             _ => panic!("No"),
@@ -60,8 +60,8 @@ pub fn get_loop(out: &mut ParseOutput) -> Vec<Field> {
         ) {
             (0, -1) => West,
             (0, 1) => East,
-            (-1, 0) => South,
-            (1, 0) => North,
+            (-1, 0) => North,
+            (1, 0) => South,
             _ => panic!("No"),
         },
     );
@@ -134,6 +134,17 @@ pub fn turn_direction(p1: (Solution, Solution), p2: (Solution, Solution)) -> Tur
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{parse, TEST_INPUT, TEST_INPUT_2};
+
+    #[test]
+    fn test_start_shape() {
+        let mut parse_output = parse(TEST_INPUT_2);
+        let start = get_loop(&mut parse_output)[0].field_type;
+        assert!(start == Pipe(South, West) || start == Pipe(West, South));
+        let mut parse_output = parse(TEST_INPUT);
+        let start = get_loop(&mut parse_output)[0].field_type;
+        assert!(start == Pipe(South, East) || start == Pipe(East, South));
+    }
 
     #[test]
     fn test_turn() {
@@ -1,15 +1,21 @@
 #![feature(test)]
 
+use crate::enclosed::{count_enclosed_pick, enclosed_tiles_scanline};
 use crate::fastest_path::{count_unmarked_fields, get_loop, turn_direction, Turn};
 use crate::grid::Dirs::{East, North, South, West};
 use crate::grid::FieldType::Pipe;
 use crate::grid::{Dirs, Field, FieldType, Grid};
+use crate::render::render;
 use std::cmp::min;
+use std::env;
 use std::fmt::Debug;
+use std::fs;
 use std::usize;
 
+pub mod enclosed;
 pub mod fastest_path;
 pub mod grid;
+pub mod render;
 type Solution = i32;
 type ParseOutput = (Grid, (usize, usize));
 const MAIN_INPUT: &str = include_str!("main_input");
@@ -53,12 +59,17 @@ fn part_1(out: &ParseOutput) -> Solution {
     get_loop(o).len() as Solution / 2
 }
 
-fn part_2(out: &ParseOutput) -> Solution {
+pub fn part_2(out: &ParseOutput) -> Solution {
+    flood_fill_enclosed(out).1.len() as Solution
+}
+
+/// The loop and the tiles it encloses. Walks the loop keeping track of the side facing the
+/// inside and flood fills from every tile next to it on that side.
+pub fn flood_fill_enclosed(out: &ParseOutput) -> (Vec<Field>, Vec<(usize, usize)>) {
     use Dirs::*;
     let o = &mut out.clone();
     let l = get_loop(o);
     let grid = &mut o.0;
-    let mut unmarked_fields = 0;
     let (mut current_normal_dir, start_i) = get_normal(&l);
     let mut from = (
         l[(start_i + 1) % l.len()].coordinate.0 as Solution - l[start_i].coordinate.0 as Solution,
@@ -72,7 +83,7 @@ fn part_2(out: &ParseOutput) -> Solution {
             next.coordinate.1 as Solution - current.coordinate.1 as Solution,
         );
         if let Some(seed) = get_field_seed(current_normal_dir, current) {
-            unmarked_fields += count_unmarked_fields(grid, seed);
+            count_unmarked_fields(grid, seed);
         }
         current_normal_dir = match turn_direction(from, to) {
             Turn::Left => match current_normal_dir {
@@ -90,11 +101,20 @@ fn part_2(out: &ParseOutput) -> Solution {
             Turn::Straight => current_normal_dir,
         };
         if let Some(seed) = get_field_seed(current_normal_dir, current) {
-            unmarked_fields += count_unmarked_fields(grid, seed);
+            count_unmarked_fields(grid, seed);
         }
         from = to;
     }
-    unmarked_fields
+    let mut on_loop = vec![vec![false; grid.get_size().1]; grid.get_size().0];
+    for field in l.iter() {
+        on_loop[field.coordinate.0][field.coordinate.1] = true;
+    }
+    let (height, width) = grid.get_size();
+    let enclosed = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (y, x)))
+        .filter(|(y, x)| grid.is_field_marked(*y, *x) && !on_loop[*y][*x])
+        .collect();
+    (l, enclosed)
 }
 
 fn get_field_seed(mut current_normal_dir: Dirs, current: Field) -> Option<(usize, usize)> {
@@ -142,7 +162,35 @@ fn get_normal(l: &Vec<Field>) -> (Dirs, usize) {
 fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
-    println!("Solution to part 2 is {}", part_2(parse_output));
+    let (l, enclosed) = flood_fill_enclosed(parse_output);
+    println!("Solution to part 2 is {}", enclosed.len());
+
+    let scanline = enclosed_tiles_scanline(&parse_output.0, &l);
+    assert_eq!(
+        count_enclosed_pick(&l) as usize,
+        enclosed.len(),
+        "Pick's theorem disagrees with the flood fill"
+    );
+    assert_eq!(
+        scanline, enclosed,
+        "The scanline disagrees with the flood fill"
+    );
+    for arg in env::args() {
+        match arg.split_once('=') {
+            Some(("--render", path)) => {
+                fs::write(path, render(&parse_output.0, &l, &enclosed, false)).unwrap()
+            }
+            _ => match arg.as_str() {
+                "--render" => print!("{}", render(&parse_output.0, &l, &enclosed, true)),
+                "--enclosed" => {
+                    for (y, x) in enclosed.iter() {
+                        println!("{},{}", y, x);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
 }
 
 #[cfg(test)]
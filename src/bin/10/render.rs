@@ -0,0 +1,95 @@
+use colored::Colorize;
+
+use crate::grid::Dirs::{East, North, South, West};
+use crate::grid::{Field, FieldType, Grid};
+
+/// Box-drawing character for a pipe, `.` for ground and `S` for a start whose shape isn't
+/// known yet.
+pub fn box_char(field_type: FieldType) -> char {
+    match field_type {
+        FieldType::Pipe(d1, d2) => match (d1 as u8) | (d2 as u8) {
+            m if m == North as u8 | South as u8 => '│',
+            m if m == East as u8 | West as u8 => '─',
+            m if m == North as u8 | East as u8 => '└',
+            m if m == North as u8 | West as u8 => '┘',
+            m if m == South as u8 | West as u8 => '┐',
+            m if m == South as u8 | East as u8 => '┌',
+            _ => panic!("No pipe connects {:?} and {:?}", d1, d2),
+        },
+        FieldType::None => '.',
+        FieldType::Start => 'S',
+    }
+}
+
+/// The maze in box-drawing characters with enclosed tiles as `I`. The start is drawn with
+/// the shape `get_loop` found for it. With `color` the loop is bold, enclosed tiles are
+/// green and junk pipes and ground are dimmed.
+pub fn render(grid: &Grid, l: &[Field], enclosed: &[(usize, usize)], color: bool) -> String {
+    let (height, width) = grid.get_size();
+    let mut on_loop = vec![vec![None; width]; height];
+    for field in l {
+        on_loop[field.coordinate.0][field.coordinate.1] = Some(field.field_type);
+    }
+    let mut is_enclosed = vec![vec![false; width]; height];
+    for (y, x) in enclosed {
+        is_enclosed[*y][*x] = true;
+    }
+    let mut out = String::new();
+    for (y, line) in grid.fields.iter().enumerate() {
+        for (x, (_, field_type, _)) in line.iter().enumerate() {
+            let tile = match on_loop[y][x] {
+                Some(loop_type) => {
+                    let tile = box_char(loop_type).to_string();
+                    if color {
+                        tile.bold().to_string()
+                    } else {
+                        tile
+                    }
+                }
+                None if is_enclosed[y][x] => {
+                    if color {
+                        "I".green().to_string()
+                    } else {
+                        "I".to_string()
+                    }
+                }
+                None => {
+                    let tile = box_char(*field_type).to_string();
+                    if color {
+                        tile.dimmed().to_string()
+                    } else {
+                        tile
+                    }
+                }
+            };
+            out.push_str(&tile);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flood_fill_enclosed, parse, TEST_INPUT};
+
+    #[test]
+    pub fn test_box_char() {
+        assert_eq!(box_char(FieldType::Pipe(North, South)), '│');
+        assert_eq!(box_char(FieldType::Pipe(West, East)), '─');
+        assert_eq!(box_char(FieldType::Pipe(East, North)), '└');
+        assert_eq!(box_char(FieldType::Pipe(South, West)), '┐');
+        assert_eq!(box_char(FieldType::None), '.');
+    }
+
+    #[test]
+    pub fn test_render() {
+        let parse_output = parse(TEST_INPUT);
+        let (l, enclosed) = flood_fill_enclosed(&parse_output);
+        assert_eq!(
+            render(&parse_output.0, &l, &enclosed, false),
+            "┐─┌┐─\n.┌┘│┐\n┌┘I└┐\n│┌──┘\n└┘.└┘\n"
+        );
+    }
+}
@@ -2,8 +2,14 @@
 #![feature(ascii_char)]
 
 use std::collections::HashMap;
+use std::env;
+use std::fs;
 use std::hash::Hash;
 
+use crate::nonogram::{print_grid, Nonogram, Solutions};
+
+pub mod nonogram;
+
 type Solution = u64;
 pub type ParseOutput = Vec<(Vec<u8>, Vec<u8>)>;
 const MAIN_INPUT: &str = include_str!("main_input");
@@ -66,17 +72,6 @@ impl Cell {
     }
 }
 
-fn get_memo<'a, K, V>(map: &'a mut HashMap<K, V>, key: &K) -> &'a mut V
-where
-    K: Clone + Eq + Hash,
-    V: Default,
-{
-    if !map.contains_key(key) {
-        map.insert(key.clone(), V::default());
-    }
-    map.get_mut(key).unwrap()
-}
-
 pub fn parse(file: &str) -> ParseOutput {
     file.lines()
         .filter(|l| !l.is_empty())
@@ -84,58 +79,81 @@ pub fn parse(file: &str) -> ParseOutput {
             let (arr, ins) = l.split_once(" ").unwrap();
             (
                 arr.as_bytes().iter().map(|n| *n).collect(),
-                ins.rsplit(",").map(|n| n.parse().unwrap()).collect(),
+                ins.split(",").map(|n| n.parse().unwrap()).collect(),
             )
         })
         .collect()
 }
 
 fn part_1(out: &ParseOutput) -> Solution {
-    let mut memoization = HashMap::new();
-    out.clone()
-        .into_iter()
-        .map(|(mut arr, ins)| {
-            let sol = solve_rec_2(
-                Cell {
-                    template: arr,
-                    inserts_left: ins,
-                    current_fill: None,
-                },
-                &mut memoization,
-            );
-            memoization.clear();
-            sol
-        })
-        .sum()
+    solve(out, 1)
 }
 
 fn part_2(out: &mut ParseOutput) -> Solution {
-    let mut memoization = HashMap::new();
-    out.clone()
-        .into_iter()
-        .map(|(mut arr, ins)| {
-            let mut arr_2 = Vec::new();
-            let mut ins_2 = Vec::new();
-            for _ in 0..5 {
-                arr_2.extend(arr.clone());
-                arr_2.push(b'?');
-                ins_2.extend(ins.clone());
-            }
-            arr_2.pop();
-            let sol = solve_rec_2(
-                Cell {
-                    template: arr_2,
-                    inserts_left: ins_2,
-                    current_fill: None,
-                },
-                &mut memoization,
-            );
-            memoization.clear();
-            sol
+    solve(out, 5)
+}
+
+fn solve(out: &ParseOutput, unfold_factor: usize) -> Solution {
+    let mut table = Vec::new();
+    out.iter()
+        .map(|(arr, ins)| {
+            let (template, groups) = unfold(arr, ins, unfold_factor);
+            count_arrangements(&template, &groups, &mut table)
         })
         .sum()
 }
 
+pub fn unfold(template: &[u8], groups: &[u8], unfold_factor: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut unfolded_template = Vec::with_capacity((template.len() + 1) * unfold_factor);
+    let mut unfolded_groups = Vec::with_capacity(groups.len() * unfold_factor);
+    for i in 0..unfold_factor {
+        if i > 0 {
+            unfolded_template.push(b'?');
+        }
+        unfolded_template.extend_from_slice(template);
+        unfolded_groups.extend_from_slice(groups);
+    }
+    (unfolded_template, unfolded_groups)
+}
+
+/// Counts the arrangements of `template` that match `groups` (in reading order).
+/// The state is (template index, group index, current run length), `table` is reused
+/// between calls so the only allocation happens when a row needs a bigger table.
+pub fn count_arrangements(template: &[u8], groups: &[u8], table: &mut Vec<Solution>) -> Solution {
+    let (len_t, len_g) = (template.len(), groups.len());
+    let max_run = groups.iter().map(|g| *g as usize).max().unwrap_or(0);
+    let (runs, per_template_index) = (max_run + 1, (len_g + 1) * (max_run + 1));
+    let index = |t: usize, g: usize, run: usize| t * per_template_index + g * runs + run;
+    table.clear();
+    table.resize((len_t + 1) * per_template_index, 0);
+
+    table[index(len_t, len_g, 0)] = 1;
+    if let Some(&last) = groups.last() {
+        table[index(len_t, len_g - 1, last as usize)] = 1;
+    }
+
+    for t in (0..len_t).rev() {
+        for g in 0..=len_g {
+            for run in 0..runs {
+                let mut arrangements = 0;
+                if template[t] != b'#' {
+                    if run == 0 {
+                        arrangements += table[index(t + 1, g, 0)];
+                    } else if g < len_g && run == groups[g] as usize {
+                        arrangements += table[index(t + 1, g + 1, 0)];
+                    }
+                }
+                if template[t] != b'.' && g < len_g && run < groups[g] as usize {
+                    arrangements += table[index(t + 1, g, run + 1)];
+                }
+                table[index(t, g, run)] = arrangements;
+            }
+        }
+    }
+
+    table[index(0, 0, 0)]
+}
+
 fn solve_rec_2(mut current_cell: Cell, memo: &mut Memoization) -> Solution {
     if let Some(&result) = memo.get(&current_cell) {
         return result;
@@ -179,10 +197,141 @@ fn solve_rec_2(mut current_cell: Cell, memo: &mut Memoization) -> Solution {
     result
 }
 
+/// Lazily walks the `solve_rec_2` state machine and yields every concrete row
+/// (only `#` and `.`) that matches the template. Branches without any arrangement
+/// are cut off through the shared memoization, so every popped leaf is a result.
+pub struct Arrangements {
+    open_cells: Vec<(Cell, Vec<u8>)>,
+    memoization: Memoization,
+}
+
+impl Arrangements {
+    pub fn new(template: &[u8], groups: &[u8]) -> Arrangements {
+        let mut arrangements = Arrangements {
+            open_cells: Vec::new(),
+            memoization: HashMap::new(),
+        };
+        let cell = Cell {
+            template: template.to_owned(),
+            inserts_left: groups.iter().rev().copied().collect(),
+            current_fill: None,
+        };
+        arrangements.push_if_solvable(cell, Vec::with_capacity(template.len()));
+        arrangements
+    }
+
+    fn push_if_solvable(&mut self, cell: Cell, row: Vec<u8>) {
+        if solve_rec_2(cell.clone(), &mut self.memoization) > 0 {
+            self.open_cells.push((cell, row));
+        }
+    }
+}
+
+impl Iterator for Arrangements {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((mut cell, mut row)) = self.open_cells.pop() {
+            if cell.template.is_empty() {
+                return Some(row);
+            }
+            match cell.template[0] {
+                b'?' => {
+                    self.push_if_solvable(cell.new_template_starting_with(b'#'), row.clone());
+                    self.push_if_solvable(cell.new_template_starting_with(b'.'), row);
+                }
+                b'#' => {
+                    if cell.consume_next_fill() {
+                        row.push(b'#');
+                        self.push_if_solvable(cell.new_template_plus_1(), row);
+                    }
+                }
+                b'.' => {
+                    if cell.reset_fill() {
+                        row.push(b'.');
+                        self.push_if_solvable(cell.new_template_plus_1(), row);
+                    }
+                }
+                _ => panic!(),
+            }
+        }
+        None
+    }
+}
+
+pub fn arrangements(
+    template: &[u8],
+    groups: &[u8],
+    limit: Option<usize>,
+) -> impl Iterator<Item = Vec<u8>> {
+    Arrangements::new(template, groups).take(limit.unwrap_or(usize::MAX))
+}
+
+/// For every cell `Some(c)` if it is `c` in all arrangements, `None` if it is ambiguous.
+/// Returns `None` for the whole row if there is no arrangement at all.
+pub fn forced_cells(template: &[u8], groups: &[u8]) -> Option<Vec<Option<u8>>> {
+    let mut table = Vec::new();
+    if count_arrangements(template, groups, &mut table) == 0 {
+        return None;
+    }
+    let mut probe = template.to_owned();
+    Some(
+        (0..template.len())
+            .map(|i| {
+                if template[i] != b'?' {
+                    return Some(template[i]);
+                }
+                probe[i] = b'#';
+                let can_be_spring = count_arrangements(&probe, groups, &mut table) > 0;
+                probe[i] = b'.';
+                let can_be_empty = count_arrangements(&probe, groups, &mut table) > 0;
+                probe[i] = b'?';
+                match (can_be_spring, can_be_empty) {
+                    (true, false) => Some(b'#'),
+                    (false, true) => Some(b'.'),
+                    _ => None,
+                }
+            })
+            .collect(),
+    )
+}
+
+pub fn explain_row(template: &[u8], groups: &[u8], limit: Option<usize>) -> String {
+    let mut explanation = String::new();
+    for row in arrangements(template, groups, limit) {
+        explanation.push_str(&format!("{}\n", String::from_utf8_lossy(&row)));
+    }
+    match forced_cells(template, groups) {
+        Some(cells) => explanation.push_str(&format!(
+            "{}\n",
+            cells
+                .iter()
+                .map(|c| c.map_or('?', |c| c as char))
+                .collect::<String>()
+        )),
+        None => explanation.push_str("no arrangement\n"),
+    }
+    explanation
+}
+
 fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
+    for arg in env::args() {
+        if let Some(("--nonogram", path)) = arg.split_once('=') {
+            let nonogram = Nonogram::parse(&fs::read_to_string(path).unwrap());
+            match nonogram.solve() {
+                Solutions::None => println!("{} has no solution", path),
+                Solutions::Unique(grid) => print_grid(&grid),
+                Solutions::Multiple(first, second) => {
+                    println!("{} has more than one solution, two of them are", path);
+                    print_grid(&first);
+                    print_grid(&second);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -228,6 +377,82 @@ mod tests {
         });
     }
 
+    #[test]
+    pub fn test_count_arrangements() {
+        let mut table = Vec::new();
+        assert_eq!(count_arrangements(b"???.###", &[1, 1, 3], &mut table), 1);
+        assert_eq!(
+            count_arrangements(b"?###????????", &[3, 2, 1], &mut table),
+            10
+        );
+        assert_eq!(count_arrangements(b"#.#", &[2], &mut table), 0);
+        assert_eq!(count_arrangements(b"...", &[], &mut table), 1);
+        let (template, groups) = unfold(b".??..??...?##.", &[1, 1, 3], 5);
+        assert_eq!(count_arrangements(&template, &groups, &mut table), 16384);
+    }
+
+    #[test]
+    pub fn test_unfold_factor() {
+        let parse_output = parse(TEST_INPUT);
+        assert_eq!(solve(&parse_output, 1), 21);
+        for (arr, ins) in &parse_output {
+            let (template, groups) = unfold(arr, ins, 2);
+            let expected = solve_rec_2(
+                Cell {
+                    template: template.clone(),
+                    inserts_left: groups.iter().rev().copied().collect(),
+                    current_fill: None,
+                },
+                &mut HashMap::new(),
+            );
+            assert_eq!(
+                count_arrangements(&template, &groups, &mut Vec::new()),
+                expected
+            );
+        }
+        assert_eq!(solve(&parse_output, 5), 525152);
+    }
+
+    #[test]
+    pub fn test_arrangements() {
+        let rows: Vec<Vec<u8>> = arrangements(b"?###????????", &[3, 2, 1], None).collect();
+        assert_eq!(rows.len(), 10);
+        assert!(rows.contains(&b".###.##.#...".to_vec()));
+        assert!(rows.contains(&b".###....##.#".to_vec()));
+        assert_eq!(
+            arrangements(b"?###????????", &[3, 2, 1], Some(3)).count(),
+            3
+        );
+        assert_eq!(arrangements(b"#.#", &[2], None).count(), 0);
+        assert_eq!(
+            arrangements(b"???.###", &[1, 1, 3], None).collect::<Vec<_>>(),
+            vec![b"#.#.###".to_vec()]
+        );
+    }
+
+    #[test]
+    pub fn test_forced_cells() {
+        assert_eq!(
+            forced_cells(b"?###????????", &[3, 2, 1]),
+            Some(vec![
+                Some(b'.'),
+                Some(b'#'),
+                Some(b'#'),
+                Some(b'#'),
+                Some(b'.'),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None
+            ])
+        );
+        assert_eq!(forced_cells(b"#.#", &[2]), None);
+        assert_eq!(explain_row(b"??", &[1], None), ".#\n#.\n??\n".to_string());
+    }
+
     #[test]
     pub fn test_solve_rec_2() {
         let mut memoization: Memoization = HashMap::new();
@@ -0,0 +1,170 @@
+use crate::forced_cells;
+
+pub type Clues = Vec<Vec<u8>>;
+pub type NonogramGrid = Vec<Vec<u8>>;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Solutions {
+    None,
+    Unique(NonogramGrid),
+    Multiple(NonogramGrid, NonogramGrid),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Nonogram {
+    pub rows: Clues,
+    pub columns: Clues,
+}
+
+impl Nonogram {
+    pub fn new(rows: Clues, columns: Clues) -> Nonogram {
+        Nonogram { rows, columns }
+    }
+
+    /// Row clues, an empty line, then column clues. One line per clue with comma
+    /// separated run lengths, `0` for an empty line.
+    pub fn parse(file: &str) -> Nonogram {
+        let (rows, columns) = file.split_once("\n\n").unwrap();
+        let parse_clues = |block: &str| -> Clues {
+            block
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(|l| {
+                    l.split(",")
+                        .map(|n| n.trim().parse().unwrap())
+                        .filter(|n| *n != 0)
+                        .collect()
+                })
+                .collect()
+        };
+        Nonogram::new(parse_clues(rows), parse_clues(columns))
+    }
+
+    pub fn solve(&self) -> Solutions {
+        let grid = vec![vec![b'?'; self.columns.len()]; self.rows.len()];
+        let mut solutions = Vec::new();
+        self.solve_rec(grid, &mut solutions);
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (None, _) => Solutions::None,
+            (Some(solution), None) => Solutions::Unique(solution),
+            (Some(first), Some(second)) => Solutions::Multiple(first, second),
+        }
+    }
+
+    /// Propagates until nothing changes, then guesses on the first unknown cell.
+    /// Stops as soon as two solutions are found, that is enough to tell it is not unique.
+    fn solve_rec(&self, mut grid: NonogramGrid, solutions: &mut Vec<NonogramGrid>) {
+        if !self.propagate(&mut grid) {
+            return;
+        }
+        let unknown = grid
+            .iter()
+            .enumerate()
+            .find_map(|(y, l)| l.iter().position(|c| *c == b'?').map(|x| (y, x)));
+        match unknown {
+            None => solutions.push(grid),
+            Some((y, x)) => {
+                for guess in *b"#." {
+                    if solutions.len() >= 2 {
+                        return;
+                    }
+                    let mut guessed_grid = grid.clone();
+                    guessed_grid[y][x] = guess;
+                    self.solve_rec(guessed_grid, solutions);
+                }
+            }
+        }
+    }
+
+    /// Runs the day 12 row solver over every row and column and fills in the cells
+    /// that are the same in all arrangements. Returns false on a contradiction.
+    fn propagate(&self, grid: &mut NonogramGrid) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, clue) in self.rows.iter().enumerate() {
+                match forced_cells(&grid[y], clue) {
+                    None => return false,
+                    Some(cells) => {
+                        for (x, cell) in cells.into_iter().enumerate() {
+                            if let Some(c) = cell {
+                                changed |= grid[y][x] != c;
+                                grid[y][x] = c;
+                            }
+                        }
+                    }
+                }
+            }
+            for (x, clue) in self.columns.iter().enumerate() {
+                let column: Vec<u8> = grid.iter().map(|l| l[x]).collect();
+                match forced_cells(&column, clue) {
+                    None => return false,
+                    Some(cells) => {
+                        for (y, cell) in cells.into_iter().enumerate() {
+                            if let Some(c) = cell {
+                                changed |= grid[y][x] != c;
+                                grid[y][x] = c;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+pub fn grid_to_string(grid: &NonogramGrid) -> String {
+    grid.iter()
+        .map(|l| format!("{}\n", l.iter().map(|c| *c as char).collect::<String>()))
+        .collect()
+}
+
+pub fn print_grid(grid: &NonogramGrid) {
+    println!("{}", grid_to_string(grid));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_unique_solution() {
+        let nonogram = Nonogram::parse("1\n3\n1,1\n\n2\n2\n2\n");
+        assert_eq!(
+            nonogram.solve(),
+            Solutions::Unique(vec![b".#.".to_vec(), b"###".to_vec(), b"#.#".to_vec()])
+        );
+    }
+
+    #[test]
+    pub fn test_needs_backtracking() {
+        let nonogram = Nonogram::parse("1,2\n1,1\n1,2\n1,1\n1,2\n\n3\n1,1\n1\n3,1\n1,2\n");
+        let mut stalled_grid = vec![vec![b'?'; 5]; 5];
+        assert!(nonogram.propagate(&mut stalled_grid));
+        assert!(stalled_grid.iter().flatten().any(|c| *c == b'?'));
+        match nonogram.solve() {
+            Solutions::Unique(grid) => assert_eq!(
+                grid_to_string(&grid),
+                ".#.##\n#..#.\n#.##.\n#...#\n.#.##\n".to_string()
+            ),
+            s => panic!("{:?}", s),
+        }
+    }
+
+    #[test]
+    pub fn test_multiple_solutions() {
+        let nonogram = Nonogram::parse("1\n1\n\n1\n1\n");
+        match nonogram.solve() {
+            Solutions::Multiple(first, second) => assert_ne!(first, second),
+            s => panic!("{:?}", s),
+        }
+    }
+
+    #[test]
+    pub fn test_no_solution() {
+        let nonogram = Nonogram::parse("2\n0\n\n1\n0\n");
+        assert_eq!(nonogram.solve(), Solutions::None);
+    }
+}
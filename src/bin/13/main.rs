@@ -1,71 +1,98 @@
 #![feature(test)]
+
+use aoc2023::bit_grid::{iter_bits, BitGrid};
+
 type Solution = usize;
-type GridLines = Vec<Vec<bool>>;
+type GridLines = BitGrid;
 type GridColumns = GridLines;
 pub type ParseOutput = Vec<(GridLines, GridColumns)>;
 const MAIN_INPUT: &str = include_str!("main_input");
 const TEST_INPUT: &str = include_str!("test_input");
 
-fn solve(grids: &ParseOutput, use_fixed: bool) -> Solution {
-    grids.iter().fold(0, |acc, (rows, columns)| {
-        acc + get_mirror_position(columns, use_fixed) + get_mirror_position(rows, use_fixed) * 100
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Mirror {
+    pub axis: Axis,
+    pub position: usize,
+    pub smudges: Vec<(usize, usize)>,
+}
+
+impl Mirror {
+    pub fn summary(&self) -> Solution {
+        match self.axis {
+            Axis::Horizontal => self.position * 100,
+            Axis::Vertical => self.position,
+        }
+    }
+}
+
+fn solve(grids: &ParseOutput, smudges: usize) -> Solution {
+    grids.iter().fold(0, |acc, pattern| {
+        acc + find_mirror(pattern, smudges).map_or(0, |mirror| mirror.summary())
+    })
+}
+
+/// Looks for a horizontal axis first, then a vertical one. Smudges are reported as
+/// (y, x) of the cell above / left of the axis.
+pub fn find_mirror((rows, columns): &(GridLines, GridColumns), smudges: usize) -> Option<Mirror> {
+    if let Some((position, differences)) = get_mirror_position(rows, smudges) {
+        return Some(Mirror {
+            axis: Axis::Horizontal,
+            position,
+            smudges: differences,
+        });
+    }
+    get_mirror_position(columns, smudges).map(|(position, differences)| Mirror {
+        axis: Axis::Vertical,
+        position,
+        smudges: differences.into_iter().map(|(x, y)| (y, x)).collect(),
     })
 }
-fn get_mirror_position(the_data: &Vec<Vec<bool>>, use_fixed: bool) -> usize {
-    'outer: for seed in 1..the_data.len() {
+
+/// Returns the first axis with exactly `smudges` differing cells and those cells as
+/// (line, index in line).
+fn get_mirror_position(the_data: &BitGrid, smudges: usize) -> Option<(usize, Vec<(usize, usize)>)> {
+    let rows = the_data.rows();
+    'outer: for seed in 1..rows.len() {
         let i_range = (0..seed).rev();
-        let i_range_rev = seed..the_data.len();
-        let mut is_fixed = false;
-        for (i, i_rev) in i_range.zip(i_range_rev) {
-            for c in 0..the_data[i].len() {
-                if the_data[i][c] != the_data[i_rev][c] {
-                    if !is_fixed {
-                        is_fixed = true;
-                    } else {
-                        continue 'outer;
-                    }
-                }
+        let i_range_rev = seed..rows.len();
+        let mut difference_count = 0;
+        for (i, i_rev) in i_range.clone().zip(i_range_rev.clone()) {
+            difference_count += the_data.row_difference(i, i_rev) as usize;
+            if difference_count > smudges {
+                continue 'outer;
             }
         }
-        if use_fixed && is_fixed {
-            return seed;
-        } else if !use_fixed && !is_fixed {
-            return seed;
+        if difference_count == smudges {
+            let differences = i_range
+                .zip(i_range_rev)
+                .flat_map(|(i, i_rev)| iter_bits(rows[i] ^ rows[i_rev]).map(move |c| (i, c)))
+                .collect();
+            return Some((seed, differences));
         }
     }
 
-    0
+    None
 }
 
 pub fn parse(file: &str) -> ParseOutput {
-    let mut grids = Vec::new();
-    let grids_str = file.split("\n\n");
-    for grid_str in grids_str {
-        let lines: Vec<Vec<bool>> = grid_str
-            .lines()
-            .map(|l| l.chars().map(|c| c == '#').collect())
-            .collect();
-        let (len_y, len_x) = (
-            grid_str.lines().count(),
-            grid_str.lines().next().unwrap().len(),
-        );
-
-        let mut columns: Vec<Vec<bool>> = Vec::new();
-        for x in 0..len_x {
-            let mut c = Vec::new();
-            for y in 0..len_y {
-                c.push(lines[y][x])
-            }
-            columns.push(c);
-        }
-        grids.push((lines, columns));
-    }
-    grids
+    file.split("\n\n")
+        .map(|grid_str| {
+            let lines = BitGrid::parse(grid_str, b'#');
+            let columns = lines.transpose();
+            (lines, columns)
+        })
+        .collect()
 }
 fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
-    println!("Solution to part 1 is {}", solve(parse_output, false));
-    println!("Solution to part 2 is {}", solve(parse_output, true));
+    println!("Solution to part 1 is {}", solve(parse_output, 0));
+    println!("Solution to part 2 is {}", solve(parse_output, 1));
 }
 
 #[cfg(test)]
@@ -77,13 +104,44 @@ mod tests {
     #[test]
     pub fn test_part_1() {
         let parse_output = parse(TEST_INPUT);
-        assert_eq!(solve(&parse_output, false), 405);
+        assert_eq!(solve(&parse_output, 0), 405);
     }
 
     #[test]
     pub fn test_part_2() {
         let parse_output = &mut parse(TEST_INPUT);
-        assert_eq!(solve(parse_output, true), 400);
+        assert_eq!(solve(parse_output, 1), 400);
+    }
+
+    #[test]
+    pub fn test_find_mirror() {
+        let parse_output = parse(TEST_INPUT);
+        assert_eq!(
+            find_mirror(&parse_output[0], 0),
+            Some(Mirror {
+                axis: Axis::Vertical,
+                position: 5,
+                smudges: vec![]
+            })
+        );
+        assert_eq!(
+            find_mirror(&parse_output[0], 1),
+            Some(Mirror {
+                axis: Axis::Horizontal,
+                position: 3,
+                smudges: vec![(0, 0)]
+            })
+        );
+        assert_eq!(
+            find_mirror(&parse_output[1], 1),
+            Some(Mirror {
+                axis: Axis::Horizontal,
+                position: 1,
+                smudges: vec![(0, 4)]
+            })
+        );
+        assert_eq!(find_mirror(&parse_output[1], 0).unwrap().position, 4);
+        assert_eq!(find_mirror(&parse(".#\n##\n")[0], 0), None);
     }
 
     #[bench]
@@ -97,7 +155,7 @@ mod tests {
     fn bench_part_1(b: &mut Bencher) {
         let parse_output = parse(MAIN_INPUT);
         b.iter(move || {
-            assert_eq!(solve(black_box(&parse_output), false), 41859);
+            assert_eq!(solve(black_box(&parse_output), 0), 41859);
         });
     }
 
@@ -105,7 +163,7 @@ mod tests {
     fn bench_part_2(b: &mut Bencher) {
         let parse_output = &mut parse(MAIN_INPUT);
         b.iter(|| {
-            assert_eq!(solve(black_box(parse_output), true), 30842);
+            assert_eq!(solve(black_box(parse_output), 1), 30842);
         });
     }
 }
@@ -1,189 +1,240 @@
 #![feature(test)]
 
-use std::collections::HashMap;
-use std::ops::Range;
+use aoc2023::bit_grid::{iter_bits, BitGrid};
+use aoc2023::cycle::state_after;
+use aoc2023::debug;
 
 type Solution = usize;
-pub type ParseOutput = Vec<Vec<u8>>;
+pub type ParseOutput = Platform;
 const MAIN_INPUT: &str = include_str!("main_input");
 const TEST_INPUT: &str = include_str!("test_input");
 
-fn part_1(grid: &ParseOutput) -> Solution {
-    let (len_y, len_x) = (grid.len(), grid[0].len());
-    let mut sol = 0;
-    for x in 0..len_x {
-        let mut current_empty_spaces = 0;
-        for y in 0..len_y {
-            let mut current_height = len_y - y;
-            match grid[y][x] {
-                b'.' => current_empty_spaces += 1,
-                b'O' => sol += current_height + current_empty_spaces,
-                b'#' => current_empty_spaces = 0,
-                _ => panic!(),
-            }
-        }
-    }
-    sol
+/// Round rocks and cube rocks as separate bit grids, only `rocks` changes while tilting.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Platform {
+    rocks: BitGrid,
+    cubes: BitGrid,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Dir {
+    North,
+    West,
+    South,
+    East,
+}
+
+fn part_1(platform: &ParseOutput) -> Solution {
+    let mut p = platform.clone();
+    tilt(&mut p, Dir::North);
+    calc_support(&p, Dir::North)
 }
 
-fn part_2(grid: &mut ParseOutput) -> Solution {
-    let mut g = grid.clone();
-    let len = grid.len();
-    let mut grids: HashMap<Vec<Vec<u8>>, usize> = HashMap::new();
+fn part_2(platform: &mut ParseOutput) -> Solution {
     let cycles = 1000000000;
-    println!("start {} <-", part_1(&g));
-    print_grid(&g);
-    for i in 1..=cycles {
-        println!("Cycle {}", i);
-        roll_rocks_north(&mut g, 0..len, 0..len);
-        roll_rocks_west(&mut g, 0..len, 0..len);
-        roll_rocks_south(&mut g, 0..len, 0..len);
-        roll_rocks_east(&mut g, 0..len, 0..len);
-        if let Some(cycle_start_i) = grids.get(&g) {
-            let cycle_length = (i - cycle_start_i);
-            println!("amount of cycles till the end {}", cycles - i);
-            println!("cycle length {}", cycle_length);
-            println!(
-                "amount of inner cycles till the end {}",
-                (cycles - i) / cycle_length
-            );
-            println!(
-                "amount of inner cycles till the end {}",
-                (cycles - i) % cycle_length
-            );
-            let index = cycle_start_i + ((cycles - i) % cycle_length);
-            println!("{} {}", cycle_start_i, cycle_length);
-            print_grid(&g);
-            g = grids
-                .into_iter()
-                .find_map(|(a, b)| if b == index { Some(a) } else { None })
-                .unwrap();
-            print_grid(&g);
-            println!("{}", calc_north_support(&g, len));
-
-            break;
-        } else {
-            grids.insert(g.clone(), i);
-        }
-    }
+    debug!("start {} <-", part_1(platform));
+    debug!("{}", grid_to_string(platform));
+    let p = run_moves(platform, &parse_moves("NWSE").unwrap(), cycles);
+    debug!("{}", grid_to_string(&p));
 
-    calc_north_support(&g, len)
+    calc_support(&p, Dir::North)
 }
 
-pub fn calc_north_support(grid: &ParseOutput, len: usize) -> Solution {
-    let mut sol = 0;
-    for x in 0..len {
-        for y in 0..len {
-            if grid[y][x] == b'O' {
-                sol += len - y;
-            }
-        }
+pub fn parse_dir(c: char) -> Result<Dir, String> {
+    match c {
+        'N' => Ok(Dir::North),
+        'W' => Ok(Dir::West),
+        'S' => Ok(Dir::South),
+        'E' => Ok(Dir::East),
+        _ => Err(format!(
+            "Unknown direction '{}', expected one of N, W, S, E",
+            c
+        )),
     }
-    sol
-}
-
-pub fn roll_rocks_north(
-    grid: &mut ParseOutput,
-    range_1: Range<Solution>,
-    range_2: Range<Solution>,
-) {
-    for r1 in range_1 {
-        let mut current_empty_spaces = 0;
-        for r2 in range_2.start..range_2.end {
-            match grid[r2][r1] {
-                b'.' => current_empty_spaces += 1,
-                b'O' => {
-                    grid[r2][r1] = b'.';
-                    grid[r2 - current_empty_spaces][r1] = b'O';
-                }
-                b'#' => current_empty_spaces = 0,
-                _ => panic!(),
+}
+
+/// "NWSE" is one spin cycle.
+pub fn parse_moves(moves: &str) -> Result<Vec<Dir>, String> {
+    moves.chars().map(parse_dir).collect()
+}
+
+/// Moves to run from `--moves=<NWSE..>`, `--repeat=<n>` and `--side=<N|W|S|E>`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MoveArgs {
+    moves: Vec<Dir>,
+    repeat: usize,
+    side: Dir,
+}
+
+impl MoveArgs {
+    /// `None` unless at least one option is given. Defaults to a single spin cycle and the
+    /// load on the north side.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Option<Result<MoveArgs, String>> {
+        let mut move_args = MoveArgs {
+            moves: vec![Dir::North, Dir::West, Dir::South, Dir::East],
+            repeat: 1,
+            side: Dir::North,
+        };
+        let mut any = false;
+        for arg in args {
+            let parsed = match arg.split_once('=') {
+                Some(("--moves", moves)) => parse_moves(moves).map(|m| move_args.moves = m),
+                Some(("--repeat", n)) => n
+                    .parse()
+                    .map(|n| move_args.repeat = n)
+                    .map_err(|e| format!("Invalid --repeat '{}': {}", n, e)),
+                Some(("--side", side)) => match side.chars().collect::<Vec<_>>()[..] {
+                    [c] => parse_dir(c).map(|d| move_args.side = d),
+                    _ => Err(format!(
+                        "Invalid --side '{}', expected one of N, W, S, E",
+                        side
+                    )),
+                },
+                _ => continue,
+            };
+            if let Err(e) = parsed {
+                return Some(Err(e));
             }
+            any = true;
         }
+        any.then_some(Ok(move_args))
     }
 }
 
-pub fn roll_rocks_west(grid: &mut ParseOutput, range_1: Range<Solution>, range_2: Range<Solution>) {
-    for r1 in range_1 {
-        let mut current_empty_spaces = 0;
-        for r2 in range_2.start..range_2.end {
-            match grid[r1][r2] {
-                b'.' => current_empty_spaces += 1,
-                b'O' => {
-                    grid[r1][r2] = b'.';
-                    grid[r1][r2 - current_empty_spaces] = b'O';
-                }
-                b'#' => current_empty_spaces = 0,
-                _ => panic!(),
-            }
+/// Tilts the platform through `moves` `repetitions` times, skipping ahead once the
+/// rocks repeat a state.
+pub fn run_moves(platform: &Platform, moves: &[Dir], repetitions: usize) -> Platform {
+    let rocks = state_after(platform.rocks.clone(), repetitions, |rocks| {
+        for dir in moves {
+            tilt_rocks(rocks, &platform.cubes, *dir);
         }
+    })
+    .state;
+    Platform {
+        rocks,
+        cubes: platform.cubes.clone(),
     }
 }
 
-pub fn roll_rocks_south(
-    grid: &mut ParseOutput,
-    range_1: Range<Solution>,
-    range_2: Range<Solution>,
-) {
-    for r1 in range_1 {
-        let mut current_empty_spaces = 0;
-        for r2 in (range_2.start..range_2.end).rev() {
-            match grid[r2][r1] {
-                b'.' => current_empty_spaces += 1,
-                b'O' => {
-                    grid[r2][r1] = b'.';
-                    grid[r2 + current_empty_spaces][r1] = b'O';
+/// Each rock weighs as much as its distance to the opposite edge of `side`, counting
+/// the row or column at that edge as 1.
+pub fn calc_support(platform: &Platform, side: Dir) -> Solution {
+    let (height, width) = (platform.rocks.height(), platform.rocks.width());
+    platform
+        .rocks
+        .rows()
+        .iter()
+        .enumerate()
+        .map(|(y, row)| match side {
+            Dir::North => row.count_ones() as Solution * (height - y),
+            Dir::South => row.count_ones() as Solution * (y + 1),
+            Dir::West => iter_bits(*row).map(|x| width - x).sum(),
+            Dir::East => iter_bits(*row).map(|x| x + 1).sum(),
+        })
+        .sum()
+}
+
+/// Vertical tilts go row by row starting at the side the rocks roll to. Everything
+/// closer to that side is settled already, so a row of rocks moves as far as it can
+/// in one go. Column `x` is bit `x`, so horizontal tilts are shifts of a whole row
+/// (west is a shift to the right), repeated until nothing moves.
+pub fn tilt(platform: &mut Platform, dir: Dir) {
+    tilt_rocks(&mut platform.rocks, &platform.cubes, dir);
+}
+
+fn tilt_rocks(rocks: &mut BitGrid, cubes: &BitGrid, dir: Dir) {
+    let mask = rocks.mask();
+    let cubes = cubes.rows();
+    let rocks = rocks.rows_mut();
+    let height = rocks.len();
+    match dir {
+        Dir::North | Dir::South => {
+            let next_y = |y: usize| match dir {
+                Dir::North => y.checked_sub(1),
+                _ => Some(y + 1).filter(|n| *n < height),
+            };
+            for i in 0..height {
+                let y = if dir == Dir::North { i } else { height - 1 - i };
+                let mut moving = rocks[y];
+                let mut current_y = y;
+                while let Some(n) = next_y(current_y) {
+                    moving &= !(rocks[n] | cubes[n]);
+                    if moving == 0 {
+                        break;
+                    }
+                    rocks[current_y] &= !moving;
+                    rocks[n] |= moving;
+                    current_y = n;
                 }
-                b'#' => current_empty_spaces = 0,
-                _ => panic!(),
             }
         }
-    }
-}
-
-pub fn roll_rocks_east(grid: &mut ParseOutput, range_1: Range<Solution>, range_2: Range<Solution>) {
-    for r1 in range_1 {
-        let mut current_empty_spaces = 0;
-        for r2 in (range_2.start..range_2.end).rev() {
-            match grid[r1][r2] {
-                b'.' => current_empty_spaces += 1,
-                b'O' => {
-                    grid[r1][r2] = b'.';
-                    grid[r1][r2 + current_empty_spaces] = b'O';
+        Dir::West | Dir::East => {
+            for (y, row) in rocks.iter_mut().enumerate() {
+                loop {
+                    let empty = !(*row | cubes[y]) & mask;
+                    let moving = match dir {
+                        Dir::West => *row & (empty << 1),
+                        _ => *row & (empty >> 1),
+                    };
+                    if moving == 0 {
+                        break;
+                    }
+                    *row &= !moving;
+                    *row |= match dir {
+                        Dir::West => moving >> 1,
+                        _ => moving << 1,
+                    };
                 }
-                b'#' => current_empty_spaces = 0,
-                _ => panic!(),
             }
         }
     }
 }
 
-pub fn print_grid(grid: &ParseOutput) {
-    println!(
-        "{}",
-        grid.iter()
-            .map(|l| format!("{}\n", l.iter().map(|c| *c as char).collect::<String>()))
-            .collect::<String>()
-    );
+pub fn grid_to_string(platform: &Platform) -> String {
+    let mut grid = String::new();
+    for y in 0..platform.rocks.height() {
+        for x in 0..platform.rocks.width() {
+            grid.push(if platform.rocks.get(y, x) {
+                'O'
+            } else if platform.cubes.get(y, x) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+        grid.push('\n');
+    }
+    grid
 }
 
 pub fn parse(file: &str) -> ParseOutput {
-    let mut grid = Vec::new();
-
-    for l in file.lines().filter(|l| !l.is_empty()) {
-        let mut line = Vec::new();
-        for character in l.as_bytes().iter() {
-            line.push(*character);
-        }
-        grid.push(line);
+    Platform {
+        rocks: BitGrid::parse(file, b'O'),
+        cubes: BitGrid::parse(file, b'#'),
     }
-
-    grid
 }
 fn main() {
+    aoc2023::trace::init();
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
+    match MoveArgs::from_args(std::env::args().skip(1)) {
+        Some(Ok(args)) => {
+            let p = run_moves(parse_output, &args.moves, args.repeat);
+            println!(
+                "Load on the {:?} side after {} x {:?} is {}",
+                args.side,
+                args.repeat,
+                args.moves,
+                calc_support(&p, args.side)
+            );
+        }
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+        None => {}
+    }
 }
 
 #[cfg(test)]
@@ -206,6 +257,71 @@ mod tests {
         assert_eq!(part_2(parse_output), 64);
     }
 
+    #[test]
+    pub fn test_spin_cycle() {
+        let mut platform = parse(TEST_INPUT);
+        platform = run_moves(&platform, &parse_moves("NWSE").unwrap(), 1);
+        let after_one_cycle = ".....#....\n....#...O#\n...OO##...\n.OO#......\n.....OOO#.\n.O#...O#.#\n....O#....\n......OOOO\n#...O###..\n#..OO#....\n";
+        assert_eq!(platform, parse(after_one_cycle));
+        let platform = run_moves(
+            &parse(TEST_INPUT),
+            &parse_moves("NWSE").unwrap(),
+            1000000000,
+        );
+        assert_eq!(calc_support(&platform, Dir::North), 64);
+    }
+
+    #[test]
+    pub fn test_rectangular_platform() {
+        let mut platform = parse("O.#O\n..O.\n.O..\n");
+        tilt(&mut platform, Dir::South);
+        assert_eq!(platform, parse("..#.\n....\nOOOO\n"));
+        assert_eq!(calc_support(&platform, Dir::North), 4);
+        assert_eq!(calc_support(&platform, Dir::South), 12);
+        tilt(&mut platform, Dir::East);
+        assert_eq!(calc_support(&platform, Dir::West), 10);
+        assert_eq!(calc_support(&platform, Dir::East), 10);
+        tilt(&mut platform, Dir::North);
+        assert_eq!(platform, parse("OO#O\n..O.\n....\n"));
+        tilt(&mut platform, Dir::West);
+        assert_eq!(platform, parse("OO#O\nO...\n....\n"));
+        assert_eq!(calc_support(&platform, Dir::West), 4 + 3 + 1 + 4);
+        assert_eq!(
+            run_moves(
+                &parse("O.#O\n..O.\n.O..\n"),
+                &parse_moves("SENW").unwrap(),
+                1
+            ),
+            platform
+        );
+    }
+
+    #[test]
+    pub fn test_move_args() {
+        let args = ["--moves=SE", "--repeat=3", "--side=W"].map(String::from);
+        assert_eq!(
+            MoveArgs::from_args(args.into_iter()),
+            Some(Ok(MoveArgs {
+                moves: vec![Dir::South, Dir::East],
+                repeat: 3,
+                side: Dir::West,
+            }))
+        );
+        assert_eq!(MoveArgs::from_args(std::iter::empty()), None);
+    }
+
+    #[test]
+    pub fn test_invalid_move_args() {
+        assert_eq!(
+            parse_moves("NWXE"),
+            Err("Unknown direction 'X', expected one of N, W, S, E".to_string())
+        );
+        for arg in ["--moves=NQ", "--repeat=-1", "--side=NW"] {
+            let result = MoveArgs::from_args([arg.to_string()].into_iter());
+            assert!(matches!(result, Some(Err(_))), "{}", arg);
+        }
+    }
+
     #[bench]
     fn bench_parse(b: &mut Bencher) {
         b.iter(|| {
@@ -225,7 +341,7 @@ mod tests {
     fn bench_part_2(b: &mut Bencher) {
         let parse_output = &mut parse(MAIN_INPUT);
         b.iter(|| {
-            assert_eq!(part_2(black_box(parse_output)), 1104);
+            assert_eq!(part_2(black_box(parse_output)), 91286);
         });
     }
 }
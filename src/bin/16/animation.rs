@@ -0,0 +1,208 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use colored::Colorize;
+
+use crate::elements::{EAST, NORTH, SOUTH, WEST};
+use crate::{BeamTracer, ParseOutput, Solution, Vector2D};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AnimationConfig {
+    pub frame_delay: Duration,
+    pub steps_per_frame: usize,
+    /// Writes every frame as plain text to `frame_00000.txt`, ... instead of drawing
+    /// it in the terminal.
+    pub dump_dir: Option<PathBuf>,
+    pub color: bool,
+    pub use_test_input: bool,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        AnimationConfig {
+            frame_delay: Duration::from_millis(30),
+            steps_per_frame: 1,
+            dump_dir: None,
+            color: true,
+            use_test_input: false,
+        }
+    }
+}
+
+impl AnimationConfig {
+    /// `None` unless `--animate` is given. Further options are `--delay=<ms>`,
+    /// `--steps=<beams per frame>`, `--dump=<dir>`, `--no-color` and `--test`.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Option<AnimationConfig> {
+        let mut config = AnimationConfig::default();
+        let mut animate = false;
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("--delay", ms)) => {
+                    config.frame_delay = Duration::from_millis(ms.parse().unwrap())
+                }
+                Some(("--steps", steps)) => config.steps_per_frame = steps.parse().unwrap(),
+                Some(("--dump", dir)) => config.dump_dir = Some(PathBuf::from(dir)),
+                _ => match arg.as_str() {
+                    "--animate" => animate = true,
+                    "--no-color" => config.color = false,
+                    "--test" => config.use_test_input = true,
+                    _ => {}
+                },
+            }
+        }
+        animate.then_some(config)
+    }
+}
+
+/// Replays part 1 frame by frame and returns the energized tile count.
+pub fn animate(
+    parse_output: &ParseOutput,
+    start_field: Vector2D<usize>,
+    start_direction: Vector2D<Solution>,
+    config: &AnimationConfig,
+) -> io::Result<Solution> {
+    let mut tracer = BeamTracer::new(parse_output, start_field, start_direction);
+    if let Some(dir) = &config.dump_dir {
+        fs::create_dir_all(dir)?;
+    }
+    let mut frame = 0;
+    show_frame(&tracer, frame, config)?;
+    while !tracer.running_beams().is_empty() {
+        let mut traced = false;
+        for _ in 0..config.steps_per_frame.max(1) {
+            if !tracer.step() {
+                break;
+            }
+            traced = true;
+        }
+        // The last beams may all have been traced before, then nothing changed
+        if traced {
+            frame += 1;
+            show_frame(&tracer, frame, config)?;
+        }
+    }
+    Ok(tracer.energized())
+}
+
+fn show_frame(tracer: &BeamTracer, frame: usize, config: &AnimationConfig) -> io::Result<()> {
+    match &config.dump_dir {
+        Some(dir) => fs::write(
+            dir.join(format!("frame_{:05}.txt", frame)),
+            render_frame(tracer, false),
+        ),
+        None => {
+            // Clear the screen and move the cursor to the top left before drawing
+            print!("\x1b[2J\x1b[H{}", render_frame(tracer, config.color));
+            println!("frame {} energized {}", frame, tracer.energized());
+            sleep(config.frame_delay);
+            Ok(())
+        }
+    }
+}
+
+/// Beam heads are arrows, energized empty tiles are `#`. With `color` energized tiles
+/// are yellow and beam heads red.
+pub fn render_frame(tracer: &BeamTracer, color: bool) -> String {
+    let grid = tracer.grid();
+    let mut heads = vec![vec![None; grid[0].len()]; grid.len()];
+    for ((y, x), direction) in tracer.running_beams() {
+        heads[*y][*x] = Some(*direction);
+    }
+    let mut frame = String::new();
+    for (y, l) in grid.iter().enumerate() {
+        for (x, (tile, entered)) in l.iter().enumerate() {
+            let tile = match heads[y][x] {
+                Some(direction) => {
+                    let arrow = match direction {
+                        NORTH => "^",
+                        EAST => ">",
+                        SOUTH => "v",
+                        WEST => "<",
+                        _ => panic!(),
+                    };
+                    if color {
+                        arrow.red().bold().to_string()
+                    } else {
+                        arrow.to_string()
+                    }
+                }
+                None if *entered != 0 => {
+                    let tile = if *tile == b'.' { '#' } else { *tile as char }.to_string();
+                    if color {
+                        tile.yellow().to_string()
+                    } else {
+                        tile
+                    }
+                }
+                None => (*tile as char).to_string(),
+            };
+            frame.push_str(&tile);
+        }
+        frame.push('\n');
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, TEST_INPUT};
+
+    #[test]
+    pub fn test_frame_snapshots() {
+        let dir = std::env::temp_dir().join(format!("aoc_16_frames_{}", std::process::id()));
+        let config = AnimationConfig {
+            dump_dir: Some(dir.clone()),
+            ..AnimationConfig::default()
+        };
+        let energized = animate(&parse(".|.\n...\n"), (0, 0), (0, 1), &config).unwrap();
+        assert_eq!(energized, 3);
+        let frames: Vec<String> = (0..4)
+            .map(|i| fs::read_to_string(dir.join(format!("frame_{:05}.txt", i))).unwrap())
+            .collect();
+        assert_eq!(
+            frames,
+            vec![">|.\n...\n", "#>.\n...\n", "#|.\n.v.\n", "#|.\n.#.\n"]
+        );
+        assert!(!dir.join("frame_00004.txt").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_one_frame_per_traced_beam() {
+        let dir = std::env::temp_dir().join(format!("aoc_16_loop_{}", std::process::id()));
+        let config = AnimationConfig {
+            dump_dir: Some(dir.clone()),
+            ..AnimationConfig::default()
+        };
+        let parse_output = parse(TEST_INPUT);
+        animate(&parse_output, (0, 0), (0, 1), &config).unwrap();
+        let mut tracer = BeamTracer::new(&parse_output, (0, 0), (0, 1));
+        while tracer.step() {}
+        let traced: usize = tracer
+            .grid()
+            .iter()
+            .flatten()
+            .map(|(_, directions)| directions.count_ones() as usize)
+            .sum();
+        let frames = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(frames, traced + 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_from_args() {
+        let args = ["--animate", "--delay=5", "--steps=3", "--no-color", "-v"];
+        let config = AnimationConfig::from_args(args.iter().map(|a| a.to_string())).unwrap();
+        assert_eq!(config.frame_delay, Duration::from_millis(5));
+        assert_eq!(config.steps_per_frame, 3);
+        assert!(!config.color && config.dump_dir.is_none());
+        assert_eq!(
+            AnimationConfig::from_args(["--test".to_string()].into_iter()),
+            None
+        );
+    }
+}
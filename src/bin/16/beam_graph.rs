@@ -0,0 +1,277 @@
+use crate::elements::{direction_index, ElementTable, DIRECTIONS};
+use crate::{get_next_field, Grid, Solution, Vector2D};
+
+const UNVISITED: usize = usize::MAX;
+
+/// One bit per cell, `y * width + x`, so grids of any width fit.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CellSet {
+    words: Vec<u64>,
+}
+
+impl CellSet {
+    pub fn new(cells: usize) -> CellSet {
+        CellSet {
+            words: vec![0; cells.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, cell: usize) {
+        self.words[cell / 64] |= 1 << (cell % 64);
+    }
+
+    pub fn contains(&self, cell: usize) -> bool {
+        self.words[cell / 64] & (1 << (cell % 64)) != 0
+    }
+
+    pub fn union_with(&mut self, other: &CellSet) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+}
+
+/// Every (cell, direction) a beam can enter with is a node, edges are where the beam
+/// continues. Nodes are condensed into strongly connected components, the energized
+/// cells are only stored for components where beams merge or split, everything in
+/// between is a plain chain that gets walked.
+pub struct BeamGraph {
+    size: (usize, usize),
+    successors: Vec<Vec<usize>>,
+    component: Vec<usize>,
+    component_nodes: Vec<Vec<usize>>,
+    component_successors: Vec<Vec<usize>>,
+    energized: Vec<Option<CellSet>>,
+}
+
+impl BeamGraph {
+    pub fn new(grid: &Grid, elements: &ElementTable) -> BeamGraph {
+        let size = (grid.len(), grid[0].len());
+        let bounds = (0..size.0 as Solution, 0..size.1 as Solution);
+        let mut successors = vec![Vec::new(); size.0 * size.1 * 4];
+        for y in 0..size.0 {
+            for x in 0..size.1 {
+                for direction in DIRECTIONS {
+                    for dir in elements.next_directions(grid[y][x].0, direction) {
+                        if let Some(next_field) = get_next_field(&(y, x), dir, &bounds) {
+                            let node = node_id(size, next_field, *dir);
+                            successors[node_id(size, (y, x), direction)].push(node);
+                        }
+                    }
+                }
+            }
+        }
+
+        let (component, component_count) = strongly_connected_components(&successors);
+        let mut component_nodes = vec![Vec::new(); component_count];
+        let mut component_successors = vec![Vec::new(); component_count];
+        for (node, c) in component.iter().enumerate() {
+            component_nodes[*c].push(node);
+            for s in &successors[node] {
+                if component[*s] != *c && !component_successors[*c].contains(&component[*s]) {
+                    component_successors[*c].push(component[*s]);
+                }
+            }
+        }
+
+        let mut graph = BeamGraph {
+            size,
+            successors,
+            component,
+            component_nodes,
+            component_successors,
+            energized: vec![None; component_count],
+        };
+        // Component ids come out of Tarjan in reverse topological order, so everything
+        // a component reaches is done before the component itself.
+        for c in 0..component_count {
+            if graph.is_stored(c) {
+                let mut energized = CellSet::new(size.0 * size.1);
+                for node in &graph.component_nodes[c] {
+                    graph.mark_cell(&mut energized, *node);
+                }
+                for s in &graph.component_successors[c] {
+                    graph.walk_chain(&mut energized, *s);
+                }
+                graph.energized[c] = Some(energized);
+            }
+        }
+        graph
+    }
+
+    /// Single nodes with at most one way to continue are chains and are not stored.
+    fn is_stored(&self, c: usize) -> bool {
+        let nodes = &self.component_nodes[c];
+        nodes.len() > 1 || self.successors[nodes[0]].len() > 1
+    }
+
+    fn mark_cell(&self, energized: &mut CellSet, node: usize) {
+        energized.insert(node / 4);
+    }
+
+    fn walk_chain(&self, energized: &mut CellSet, mut c: usize) {
+        loop {
+            if let Some(stored) = &self.energized[c] {
+                energized.union_with(stored);
+                return;
+            }
+            let node = self.component_nodes[c][0];
+            self.mark_cell(energized, node);
+            match self.component_successors[c].first() {
+                Some(next) => c = *next,
+                None => return,
+            }
+        }
+    }
+
+    pub fn energized(
+        &self,
+        start_field: Vector2D<usize>,
+        start_direction: Vector2D<Solution>,
+    ) -> CellSet {
+        let mut energized = CellSet::new(self.size.0 * self.size.1);
+        let node = node_id(self.size, start_field, start_direction);
+        self.walk_chain(&mut energized, self.component[node]);
+        energized
+    }
+
+    pub fn energized_count(
+        &self,
+        start_field: Vector2D<usize>,
+        start_direction: Vector2D<Solution>,
+    ) -> Solution {
+        self.energized(start_field, start_direction).count_ones() as Solution
+    }
+
+    /// All 2 * (w + h) beams that enter from the edge.
+    pub fn entry_points(&self) -> Vec<(Vector2D<usize>, Vector2D<Solution>)> {
+        let (y_max, x_max) = (self.size.0 - 1, self.size.1 - 1);
+        let mut entries = Vec::new();
+        for y in 0..=y_max {
+            entries.push(((y, 0), (0, 1)));
+            entries.push(((y, x_max), (0, -1)));
+        }
+        for x in 0..=x_max {
+            entries.push(((0, x), (1, 0)));
+            entries.push(((y_max, x), (-1, 0)));
+        }
+        entries
+    }
+}
+
+fn node_id(
+    (_, len_x): (usize, usize),
+    (y, x): Vector2D<usize>,
+    direction: Vector2D<Solution>,
+) -> usize {
+    (y * len_x + x) * 4 + direction_index(direction)
+}
+
+/// Iterative Tarjan. Returns the component of every node and the number of components,
+/// components are numbered in reverse topological order.
+fn strongly_connected_components(successors: &[Vec<usize>]) -> (Vec<usize>, usize) {
+    let n = successors.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut component = vec![UNVISITED; n];
+    let mut component_count = 0;
+    let mut counter = 0;
+    let mut calls: Vec<(usize, usize)> = Vec::new();
+    for start in 0..n {
+        if index[start] != UNVISITED {
+            continue;
+        }
+        index[start] = counter;
+        low[start] = counter;
+        counter += 1;
+        stack.push(start);
+        on_stack[start] = true;
+        calls.push((start, 0));
+        while let Some((v, i)) = calls.last_mut() {
+            let v = *v;
+            if *i < successors[v].len() {
+                let w = successors[v][*i];
+                *i += 1;
+                if index[w] == UNVISITED {
+                    index[w] = counter;
+                    low[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some((u, _)) = calls.last() {
+                low[*u] = low[*u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component[w] = component_count;
+                    if w == v {
+                        break;
+                    }
+                }
+                component_count += 1;
+            }
+        }
+    }
+    (component, component_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, part_1, TEST_INPUT};
+
+    #[test]
+    pub fn test_matches_tracing() {
+        let custom_input = ".*..\\X\n..|.>.\n-..*..\n.X..<.\n/.-..*\n..>.|.\n\nX absorber\n* prism\n> gate E\n< gate W\n";
+        for input in [TEST_INPUT, custom_input] {
+            let parse_output = parse(input);
+            let graph = BeamGraph::new(&parse_output.0, &parse_output.1);
+            for (start_field, start_direction) in graph.entry_points() {
+                assert_eq!(
+                    graph.energized_count(start_field, start_direction),
+                    part_1(&parse_output, start_field, start_direction),
+                    "{:?} {:?}",
+                    start_field,
+                    start_direction
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_wider_than_128() {
+        let input = format!("{}\n{}\n", ".".repeat(200), "-".repeat(200));
+        let parse_output = parse(&input);
+        let graph = BeamGraph::new(&parse_output.0, &parse_output.1);
+        let energized = graph.energized((0, 150), (1, 0));
+        assert!(energized.contains(150) && energized.contains(200 + 199));
+        assert!(!energized.contains(149));
+        assert_eq!(energized.count_ones(), 201);
+        assert_eq!(
+            graph.energized_count((0, 150), (1, 0)),
+            part_1(&parse_output, (0, 150), (1, 0))
+        );
+    }
+
+    #[test]
+    pub fn test_components() {
+        let successors = vec![vec![1], vec![2], vec![0, 3], vec![]];
+        let (component, count) = strongly_connected_components(&successors);
+        assert_eq!(count, 2);
+        assert_eq!(component, vec![1, 1, 1, 0]);
+    }
+}
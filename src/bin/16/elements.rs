@@ -0,0 +1,151 @@
+use crate::{Solution, Vector2D};
+
+pub const NORTH: Vector2D<Solution> = (-1, 0);
+pub const EAST: Vector2D<Solution> = (0, 1);
+pub const SOUTH: Vector2D<Solution> = (1, 0);
+pub const WEST: Vector2D<Solution> = (0, -1);
+pub const DIRECTIONS: [Vector2D<Solution>; 4] = [NORTH, EAST, SOUTH, WEST];
+
+pub fn direction_index(direction: Vector2D<Solution>) -> usize {
+    match direction {
+        NORTH => 0,
+        EAST => 1,
+        SOUTH => 2,
+        WEST => 3,
+        _ => panic!("Not a direction {:?}", direction),
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Element {
+    Empty,
+    /// `/`
+    SlashMirror,
+    /// `\`
+    BackslashMirror,
+    /// `|`
+    VerticalSplitter,
+    /// `-`
+    HorizontalSplitter,
+    Absorber,
+    /// Lets beams through that travel in the given direction, absorbs everything else.
+    Gate(Vector2D<Solution>),
+    /// Keeps the beam going and also splits it off to both sides.
+    Prism,
+}
+
+impl Element {
+    pub fn outputs(&self, (d_y, d_x): Vector2D<Solution>) -> Vec<Vector2D<Solution>> {
+        let left = (-d_x, d_y);
+        let right = (d_x, -d_y);
+        match self {
+            Element::Empty => vec![(d_y, d_x)],
+            Element::SlashMirror => vec![(-d_x, -d_y)],
+            Element::BackslashMirror => vec![(d_x, d_y)],
+            Element::VerticalSplitter if d_y == 0 => vec![NORTH, SOUTH],
+            Element::HorizontalSplitter if d_x == 0 => vec![WEST, EAST],
+            Element::VerticalSplitter | Element::HorizontalSplitter => vec![(d_y, d_x)],
+            Element::Absorber => vec![],
+            Element::Gate(allowed) if *allowed == (d_y, d_x) => vec![(d_y, d_x)],
+            Element::Gate(_) => vec![],
+            Element::Prism => vec![(d_y, d_x), left, right],
+        }
+    }
+
+    /// `absorber`, `gate <N|E|S|W>`, `prism`, `empty`, `mirror </|\>` or `splitter <||->`.
+    pub fn parse(description: &str) -> Element {
+        let mut words = description.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("empty"), None) => Element::Empty,
+            (Some("absorber"), None) => Element::Absorber,
+            (Some("prism"), None) => Element::Prism,
+            (Some("mirror"), Some("/")) => Element::SlashMirror,
+            (Some("mirror"), Some("\\")) => Element::BackslashMirror,
+            (Some("splitter"), Some("|")) => Element::VerticalSplitter,
+            (Some("splitter"), Some("-")) => Element::HorizontalSplitter,
+            (Some("gate"), Some(dir)) => Element::Gate(match dir {
+                "N" => NORTH,
+                "E" => EAST,
+                "S" => SOUTH,
+                "W" => WEST,
+                d => panic!("Unknown gate direction '{}'", d),
+            }),
+            _ => panic!("Unknown element '{}'", description),
+        }
+    }
+}
+
+/// Maps every tile byte to its element, with the outputs for all four incoming
+/// directions computed up front.
+#[derive(Debug, Clone)]
+pub struct ElementTable {
+    outputs: Vec<Option<[Vec<Vector2D<Solution>>; 4]>>,
+}
+
+impl Default for ElementTable {
+    fn default() -> Self {
+        let mut table = ElementTable {
+            outputs: vec![None; 256],
+        };
+        table.insert(b'.', Element::Empty);
+        table.insert(b'/', Element::SlashMirror);
+        table.insert(b'\\', Element::BackslashMirror);
+        table.insert(b'|', Element::VerticalSplitter);
+        table.insert(b'-', Element::HorizontalSplitter);
+        table
+    }
+}
+
+impl ElementTable {
+    pub fn insert(&mut self, tile: u8, element: Element) {
+        self.outputs[tile as usize] = Some(DIRECTIONS.map(|d| element.outputs(d)));
+    }
+
+    /// One `<tile> <element>` per line, see `Element::parse`.
+    pub fn parse_legend(&mut self, legend: &str) {
+        for l in legend.lines().filter(|l| !l.trim().is_empty()) {
+            let (tile, description) = l.split_once(' ').unwrap();
+            assert_eq!(tile.len(), 1, "Tiles are a single character '{}'", tile);
+            self.insert(tile.as_bytes()[0], Element::parse(description));
+        }
+    }
+
+    pub fn next_directions(
+        &self,
+        tile: u8,
+        direction: Vector2D<Solution>,
+    ) -> &[Vector2D<Solution>] {
+        match &self.outputs[tile as usize] {
+            Some(outputs) => &outputs[direction_index(direction)],
+            None => panic!("Unknown element '{}'", tile as char),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_builtin_elements() {
+        let table = ElementTable::default();
+        assert_eq!(table.next_directions(b'.', EAST), &[EAST]);
+        assert_eq!(table.next_directions(b'/', EAST), &[NORTH]);
+        assert_eq!(table.next_directions(b'/', SOUTH), &[WEST]);
+        assert_eq!(table.next_directions(b'\\', EAST), &[SOUTH]);
+        assert_eq!(table.next_directions(b'\\', NORTH), &[WEST]);
+        assert_eq!(table.next_directions(b'|', EAST), &[NORTH, SOUTH]);
+        assert_eq!(table.next_directions(b'|', NORTH), &[NORTH]);
+        assert_eq!(table.next_directions(b'-', SOUTH), &[WEST, EAST]);
+    }
+
+    #[test]
+    pub fn test_legend() {
+        let mut table = ElementTable::default();
+        table.parse_legend("X absorber\n> gate E\n* prism\n");
+        assert!(table.next_directions(b'X', NORTH).is_empty());
+        assert_eq!(table.next_directions(b'>', EAST), &[EAST]);
+        assert!(table.next_directions(b'>', WEST).is_empty());
+        assert_eq!(table.next_directions(b'*', EAST), &[EAST, NORTH, SOUTH]);
+    }
+}
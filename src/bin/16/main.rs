@@ -1,98 +1,105 @@
 #![feature(test)]
 
-use std::cmp::max;
+use std::env;
 use std::ops::Range;
 
+use crate::animation::{animate, AnimationConfig};
+use crate::beam_graph::BeamGraph;
+use crate::elements::{direction_index, ElementTable};
+
+mod animation;
+mod beam_graph;
+mod elements;
+
 type Solution = i32;
-type Grid = Vec<Vec<(u8, bool, bool)>>;
+/// Tile and a bitmask of the directions (see `elements::DIRECTIONS`) a beam entered with.
+type Grid = Vec<Vec<(u8, u8)>>;
 type Vector2D<T> = (T, T);
 type Beam = (Vector2D<usize>, Vector2D<Solution>);
-pub type ParseOutput = Grid;
+pub type ParseOutput = (Grid, ElementTable);
 const MAIN_INPUT: &str = include_str!("main_input");
 const TEST_INPUT: &str = include_str!("test_input");
 
-fn part_1(
-    grid: &ParseOutput,
-    start_field: Vector2D<usize>,
-    start_direction: Vector2D<Solution>,
-) -> Solution {
-    let g = &mut grid.clone();
-    let max = (0..g.len() as Solution, 0..g[0].len() as Solution);
-    let mut running_beams: Vec<Beam> = vec![(start_field, start_direction)];
-    while let Some(((y, x), direction)) = running_beams.pop() {
-        g[y][x].1 = true;
-        let (next_dir, split) = get_next_directions(direction, &g[y][x].0);
-        if split.is_some() {
-            // Continue if we already split on this field
-            if g[y][x].2 == true {
-                continue;
+/// Runs the beams of one start off the `running_beams` stack, one beam per `step`.
+pub struct BeamTracer<'a> {
+    grid: Grid,
+    elements: &'a ElementTable,
+    max: Vector2D<Range<Solution>>,
+    running_beams: Vec<Beam>,
+}
+
+impl<'a> BeamTracer<'a> {
+    pub fn new(
+        (grid, elements): &'a ParseOutput,
+        start_field: Vector2D<usize>,
+        start_direction: Vector2D<Solution>,
+    ) -> BeamTracer<'a> {
+        BeamTracer {
+            grid: grid.clone(),
+            elements,
+            max: (0..grid.len() as Solution, 0..grid[0].len() as Solution),
+            running_beams: vec![(start_field, start_direction)],
+        }
+    }
+
+    /// Traces one beam through one tile. Beams that already went through their tile in
+    /// their direction would do the same again and are dropped on the way. Returns false
+    /// once there is no beam left to trace.
+    pub fn step(&mut self) -> bool {
+        let ((y, x), direction, direction_bit) = loop {
+            let Some(((y, x), direction)) = self.running_beams.pop() else {
+                return false;
+            };
+            let direction_bit = 1 << direction_index(direction);
+            if self.grid[y][x].1 & direction_bit == 0 {
+                break ((y, x), direction, direction_bit);
+            }
+        };
+        self.grid[y][x].1 |= direction_bit;
+        for next_dir in self.elements.next_directions(self.grid[y][x].0, direction) {
+            if let Some(next_field) = get_next_field(&(y, x), next_dir, &self.max) {
+                self.running_beams.push((next_field, *next_dir));
             }
-            g[y][x].2 = true;
         }
-        split.and_then(|split_dir| {
-            get_next_field(&(y, x), &split_dir, &max)
-                .map(|next_field| running_beams.push((next_field, split_dir)))
-        });
-        get_next_field(&(y, x), &next_dir, &max)
-            .map(|next_field| running_beams.push((next_field, next_dir)));
+        true
     }
-    g.iter()
-        .map(|l| l.iter().filter(|c| c.1).count() as Solution)
-        .sum()
-}
 
-fn part_2(grid: &ParseOutput) -> Solution {
-    let (y_max, x_max) = (grid.len() - 1, grid[0].len() - 1);
-    let mut max_tiles = Solution::MIN;
-    let right = (0, 1);
-    let left = (0, -1);
-    let top = (-1, 0);
-    let bottom = (1, 0);
-    for y in 0..=y_max {
-        max_tiles = max(max_tiles, part_1(grid, (y, 0), right));
-        max_tiles = max(max_tiles, part_1(grid, (y, x_max), left));
+    pub fn grid(&self) -> &Grid {
+        &self.grid
     }
-    for x in 0..=x_max {
-        max_tiles = max(max_tiles, part_1(grid, (0, x), bottom));
-        max_tiles = max(max_tiles, part_1(grid, (y_max, x), top));
+
+    pub fn running_beams(&self) -> &[Beam] {
+        &self.running_beams
     }
-    max_tiles
-}
 
-fn get_next_directions(
-    old_dir: Vector2D<Solution>,
-    c: &u8,
-) -> (Vector2D<Solution>, Option<Vector2D<Solution>>) {
-    match c {
-        b'|' => match old_dir {
-            (0, 1) | (0, -1) => ((-1, 0), Some((1, 0))),
-            (1, 0) | (-1, 0) => (old_dir, None),
-            _ => panic!(),
-        },
-        b'-' => match old_dir {
-            (1, 0) | (-1, 0) => ((0, -1), Some((0, 1))),
-            (0, 1) | (0, -1) => (old_dir, None),
-            _ => panic!(),
-        },
-        b'/' => match old_dir {
-            (1, 0) => ((0, -1), None),
-            (-1, 0) => ((0, 1), None),
-            (0, 1) => ((-1, 0), None),
-            (0, -1) => ((1, 0), None),
-            _ => panic!(),
-        },
-        b'\\' => match old_dir {
-            (1, 0) => ((0, 1), None),
-            (-1, 0) => ((0, -1), None),
-            (0, 1) => ((1, 0), None),
-            (0, -1) => ((-1, 0), None),
-            _ => panic!(),
-        },
-        b'.' => (old_dir, None),
-        _ => panic!(),
+    pub fn energized(&self) -> Solution {
+        self.grid
+            .iter()
+            .map(|l| l.iter().filter(|c| c.1 != 0).count() as Solution)
+            .sum()
     }
 }
 
+fn part_1(
+    parse_output: &ParseOutput,
+    start_field: Vector2D<usize>,
+    start_direction: Vector2D<Solution>,
+) -> Solution {
+    let mut tracer = BeamTracer::new(parse_output, start_field, start_direction);
+    while tracer.step() {}
+    tracer.energized()
+}
+
+fn part_2((grid, elements): &ParseOutput) -> Solution {
+    let graph = BeamGraph::new(grid, elements);
+    graph
+        .entry_points()
+        .into_iter()
+        .map(|(start_field, start_direction)| graph.energized_count(start_field, start_direction))
+        .max()
+        .unwrap()
+}
+
 fn get_next_field(
     (c_y, c_x): &Vector2D<usize>,
     (d_y, d_x): &Vector2D<Solution>,
@@ -106,13 +113,29 @@ fn get_next_field(
     None
 }
 
+/// The grid, optionally followed by an empty line and a legend for extra elements.
 pub fn parse(file: &str) -> ParseOutput {
-    file.lines()
-        .filter(|l| !l.is_empty())
-        .map(|l| l.as_bytes().iter().map(|c| (*c, false, false)).collect())
-        .collect()
+    let (grid, legend) = file.split_once("\n\n").unwrap_or((file, ""));
+    let mut elements = ElementTable::default();
+    elements.parse_legend(legend);
+    (
+        grid.lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.as_bytes().iter().map(|c| (*c, 0)).collect())
+            .collect(),
+        elements,
+    )
 }
 fn main() {
+    if let Some(config) = AnimationConfig::from_args(env::args().skip(1)) {
+        let input = if config.use_test_input {
+            TEST_INPUT
+        } else {
+            MAIN_INPUT
+        };
+        animate(&parse(input), (0, 0), (0, 1), &config).unwrap();
+        return;
+    }
     let parse_output = &mut parse(MAIN_INPUT);
     println!(
         "Solution to part 1 is {}",
@@ -139,6 +162,21 @@ mod tests {
         assert_eq!(part_2(parse_output), 51);
     }
 
+    #[test]
+    pub fn test_custom_elements() {
+        let parse_output =
+            parse("..X.\n.*..\n.>..\n.|<.\n\nX absorber\n* prism\n> gate E\n< gate W\n");
+        assert_eq!(part_1(&parse_output, (0, 0), (0, 1)), 3);
+        assert_eq!(part_1(&parse_output, (1, 0), (0, 1)), 6);
+        assert_eq!(part_1(&parse_output, (3, 3), (0, -1)), 4);
+    }
+
+    #[test]
+    pub fn test_loops_terminate() {
+        let parse_output = parse("*..*\n....\n*..*\n\n* prism\n");
+        assert_eq!(part_1(&parse_output, (0, 0), (0, 1)), 10);
+    }
+
     #[bench]
     fn bench_parse(b: &mut Bencher) {
         b.iter(|| {
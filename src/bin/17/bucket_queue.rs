@@ -0,0 +1,62 @@
+/// Dial's priority queue for searches whose priorities never decrease and grow by at most
+/// `span - 1` per step. Every push has to be in `current..current + span`, with `current`
+/// the priority that was popped last, or `start` before the first pop.
+#[derive(Debug, Clone)]
+pub struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    current: usize,
+    len: usize,
+}
+
+impl<T> BucketQueue<T> {
+    pub fn new(start: usize, span: usize) -> BucketQueue<T> {
+        BucketQueue {
+            buckets: (0..span.max(1)).map(|_| Vec::new()).collect(),
+            current: start,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, priority: usize, item: T) {
+        debug_assert!(priority >= self.current && priority < self.current + self.buckets.len());
+        let bucket = priority % self.buckets.len();
+        self.buckets[bucket].push(item);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<(usize, T)> {
+        if self.len == 0 {
+            return None;
+        }
+        loop {
+            let bucket = self.current % self.buckets.len();
+            if let Some(item) = self.buckets[bucket].pop() {
+                self.len -= 1;
+                return Some((self.current, item));
+            }
+            self.current += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_pops_in_order() {
+        let mut queue = BucketQueue::new(1, 10);
+        queue.push(3, 'c');
+        queue.push(9, 'e');
+        queue.push(1, 'a');
+        queue.push(3, 'd');
+        assert_eq!(queue.pop(), Some((1, 'a')));
+        queue.push(2, 'b');
+        queue.push(10, 'f');
+        let popped: Vec<(usize, char)> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(
+            popped,
+            vec![(2, 'b'), (3, 'd'), (3, 'c'), (9, 'e'), (10, 'f')]
+        );
+    }
+}
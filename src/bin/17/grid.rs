@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
 
+use crate::rules::{Direction, MovementRules};
 use crate::Solution;
 
 pub type CostType = Solution;
@@ -46,6 +47,58 @@ impl Grid {
         self.fields[y][x].1
     }
 
+    pub fn get_neighbour(
+        &self,
+        y: usize,
+        x: usize,
+        (d_y, d_x): Direction,
+    ) -> Option<(usize, usize, CostType)> {
+        let (n_y, n_x) = (y as i32 + d_y, x as i32 + d_x);
+        if !self.is_field_in_bounds(n_y, n_x) {
+            return None;
+        }
+
+        Some((
+            n_y as usize,
+            n_x as usize,
+            self.get_field_cost(n_y as usize, n_x as usize),
+        ))
+    }
+
+    /// Every move the rules allow from `(y, x)` after `old_run` steps in direction
+    /// `old_dir`, an index into `rules.directions()`, as (y, x, heat loss, direction, run).
+    /// The start has no direction and may go anywhere.
+    pub fn get_neighbours<'a>(
+        &'a self,
+        y: usize,
+        x: usize,
+        old_dir: Option<usize>,
+        old_run: i32,
+        rules: &'a MovementRules,
+    ) -> impl Iterator<Item = (usize, usize, CostType, usize, i32)> + 'a {
+        let directions = rules.directions();
+        directions
+            .iter()
+            .enumerate()
+            .filter_map(move |(new_dir, &(d_y, d_x))| {
+                let turned = old_dir.is_some_and(|d| d != new_dir);
+                let reversed = old_dir.is_some_and(|d| directions[d] == (-d_y, -d_x));
+                if (turned && old_run < rules.min_run) || (reversed && !rules.allow_reverse) {
+                    return None;
+                }
+                let new_run = if old_dir == Some(new_dir) {
+                    old_run + 1
+                } else {
+                    1
+                };
+                if new_run > rules.max_run {
+                    return None;
+                }
+                self.get_neighbour(y, x, (d_y, d_x))
+                    .map(|(n_y, n_x, n)| (n_y, n_x, n, new_dir, new_run))
+            })
+    }
+
     pub fn get_left(&self, y: usize, x: usize) -> Option<CostType> {
         if !self.is_field_in_bounds(y as i32, x as i32 - 1) {
             return None;
@@ -94,73 +147,73 @@ impl Grid {
             self.get_right(y, x),
             self.get_bottom(y, x),
         );
-        if let Some(n) = left_o
-            && !(y_d == 0 && x_d == 1)
-        {
-            let new_dir = (0, -1);
-            let new_straights = if new_dir == old_dir {
-                old_straights + 1
-            } else {
-                0
-            };
-            let turned = new_straights == 0;
-            if old_dir == (0, 0)
-                || !turned
-                || (turned && old_straights >= min_straights_before_turn)
-            {
-                neighbours.push((y, x - 1, n, new_dir, new_straights));
+        if let Some(n) = left_o {
+            if !(y_d == 0 && x_d == 1) {
+                let new_dir = (0, -1);
+                let new_straights = if new_dir == old_dir {
+                    old_straights + 1
+                } else {
+                    0
+                };
+                let turned = new_straights == 0;
+                if old_dir == (0, 0)
+                    || !turned
+                    || (turned && old_straights >= min_straights_before_turn)
+                {
+                    neighbours.push((y, x - 1, n, new_dir, new_straights));
+                }
             }
         };
-        if let Some(n) = right_o
-            && !(y_d == 0 && x_d == -1)
-        {
-            let new_dir = (0, 1);
-            let new_straights = if new_dir == old_dir {
-                old_straights + 1
-            } else {
-                0
-            };
-            let turned = new_straights == 0;
-            if old_dir == (0, 0)
-                || !turned
-                || (turned && old_straights >= min_straights_before_turn)
-            {
-                neighbours.push((y, x + 1, n, new_dir, new_straights));
+        if let Some(n) = right_o {
+            if !(y_d == 0 && x_d == -1) {
+                let new_dir = (0, 1);
+                let new_straights = if new_dir == old_dir {
+                    old_straights + 1
+                } else {
+                    0
+                };
+                let turned = new_straights == 0;
+                if old_dir == (0, 0)
+                    || !turned
+                    || (turned && old_straights >= min_straights_before_turn)
+                {
+                    neighbours.push((y, x + 1, n, new_dir, new_straights));
+                }
             }
         };
-        if let Some(n) = top_o
-            && !(y_d == 1 && x_d == 0)
-        {
-            let new_dir = (-1, 0);
-            let new_straights = if new_dir == old_dir {
-                old_straights + 1
-            } else {
-                0
-            };
-
-            let turned = new_straights == 0;
-            if old_dir == (0, 0)
-                || !turned
-                || (turned && old_straights >= min_straights_before_turn)
-            {
-                neighbours.push((y - 1, x, n, new_dir, new_straights));
+        if let Some(n) = top_o {
+            if !(y_d == 1 && x_d == 0) {
+                let new_dir = (-1, 0);
+                let new_straights = if new_dir == old_dir {
+                    old_straights + 1
+                } else {
+                    0
+                };
+
+                let turned = new_straights == 0;
+                if old_dir == (0, 0)
+                    || !turned
+                    || (turned && old_straights >= min_straights_before_turn)
+                {
+                    neighbours.push((y - 1, x, n, new_dir, new_straights));
+                }
             }
         };
-        if let Some(n) = bottom_o
-            && !(y_d == -1 && x_d == 0)
-        {
-            let new_dir = (1, 0);
-            let new_straights = if new_dir == old_dir {
-                old_straights + 1
-            } else {
-                0
-            };
-            let turned = new_straights == 0;
-            if old_dir == (0, 0)
-                || !turned
-                || (turned && old_straights >= min_straights_before_turn)
-            {
-                neighbours.push((y + 1, x, n, new_dir, new_straights));
+        if let Some(n) = bottom_o {
+            if !(y_d == -1 && x_d == 0) {
+                let new_dir = (1, 0);
+                let new_straights = if new_dir == old_dir {
+                    old_straights + 1
+                } else {
+                    0
+                };
+                let turned = new_straights == 0;
+                if old_dir == (0, 0)
+                    || !turned
+                    || (turned && old_straights >= min_straights_before_turn)
+                {
+                    neighbours.push((y + 1, x, n, new_dir, new_straights));
+                }
             }
         };
         neighbours
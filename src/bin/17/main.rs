@@ -1,9 +1,11 @@
 #![feature(test)]
-#![feature(let_chains)]
 
 use std::collections::{BinaryHeap, HashMap};
+use std::env;
 
+use crate::bucket_queue::BucketQueue;
 use crate::grid::{Field, Grid};
+use crate::rules::MovementRules;
 
 type Solution = u32;
 pub type ParseOutput = Grid;
@@ -16,13 +18,19 @@ struct Cell {
     straights: i32,
     direction: (i32, i32),
 }
+mod bucket_queue;
 mod grid;
+mod rules;
 fn part_1(grid: &ParseOutput) -> Solution {
-    calc_best_path(grid.clone(), 0, 3)
+    calc_best_path(grid, &MovementRules::crucible(), true)
+        .unwrap()
+        .0
 }
 
 fn part_2(grid: &mut ParseOutput) -> Solution {
-    calc_best_path(grid.clone(), 3, 10)
+    calc_best_path(grid, &MovementRules::ultra_crucible(), true)
+        .unwrap()
+        .0
 }
 
 pub fn parse(file: &str) -> ParseOutput {
@@ -40,15 +48,142 @@ fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
+    let render = env::args().any(|a| a == "--render");
+    let custom_rules = MovementRules::from_args(env::args().skip(1));
+    if let Some(rules) = &custom_rules {
+        match calc_best_path(parse_output, rules, true) {
+            Some((cost, path)) => {
+                println!("Best path with {:?} costs {}", rules, cost);
+                if render {
+                    parse_output.print_grid(&path);
+                }
+            }
+            None => println!("No path with {:?}", rules),
+        }
+    } else if render {
+        for rules in [MovementRules::crucible(), MovementRules::ultra_crucible()] {
+            if let Some((_, path)) = calc_best_path(parse_output, &rules, true) {
+                parse_output.print_grid(&path);
+                println!();
+            }
+        }
+    }
+}
+
+/// Returns the cost, heat loss plus turn penalties, and the cells of the best path from
+/// the start to the goal, both ends included. `None` if the goal can't be reached or
+/// either end is off the grid.
+///
+/// Dijkstra over a bucket queue, edge costs are small integers. States are (y, x, dir,
+/// run) indexed into flat arrays. With `use_heuristic` this is A* with the distance to the
+/// goal times the cheapest cell. It never overestimates and never drops by more than the
+/// cost of an edge, so priorities still only grow.
+fn calc_best_path(
+    grid: &ParseOutput,
+    rules: &MovementRules,
+    use_heuristic: bool,
+) -> Option<(Solution, Vec<(usize, usize)>)> {
+    let (height, width) = grid.get_size();
+    let goal = rules.goal.unwrap_or((height - 1, width - 1));
+    let in_bounds = |(y, x): (usize, usize)| y < height && x < width;
+    if !in_bounds(rules.start) || !in_bounds(goal) {
+        return None;
+    }
+    if rules.start == goal {
+        return Some((0, vec![goal]));
+    }
+    let directions = rules.directions().len();
+    let runs = rules.max_run.max(1) as usize;
+    let state_index = |(y, x): (usize, usize), dir: usize, run: i32| {
+        ((y * width + x) * directions + dir) * runs + run as usize - 1
+    };
+    let state_position = |state: usize| {
+        let cell = state / runs / directions;
+        (cell / width, cell % width)
+    };
+    let cheapest_field = grid.fields.iter().flatten().map(|f| f.0).min().unwrap();
+    let most_expensive_field = grid.fields.iter().flatten().map(|f| f.0).max().unwrap();
+    let heuristic = |(y, x): (usize, usize)| {
+        if !use_heuristic {
+            return 0;
+        }
+        let (d_y, d_x) = (y.abs_diff(goal.0), x.abs_diff(goal.1));
+        let steps = if rules.diagonals {
+            d_y.max(d_x)
+        } else {
+            d_y + d_x
+        };
+        steps as Solution * cheapest_field
+    };
+
+    let mut best_costs = vec![Solution::MAX; height * width * directions * runs];
+    let mut parents = vec![u32::MAX; best_costs.len()];
+    let mut done = vec![false; best_costs.len()];
+    let mut open_states = BucketQueue::new(
+        heuristic(rules.start) as usize,
+        (most_expensive_field + rules.turn_penalty + cheapest_field) as usize + 1,
+    );
+    for (y, x, heat_loss, dir, run) in
+        grid.get_neighbours(rules.start.0, rules.start.1, None, 0, rules)
+    {
+        let state = state_index((y, x), dir, run);
+        best_costs[state] = heat_loss;
+        open_states.push((heat_loss + heuristic((y, x))) as usize, state);
+    }
+    while let Some((_, state)) = open_states.pop() {
+        if done[state] {
+            continue;
+        }
+        done[state] = true;
+        let (y, x) = state_position(state);
+        let dir = state / runs % directions;
+        let run = (state % runs) as i32 + 1;
+        if (y, x) == goal && run >= rules.min_run {
+            let mut path = vec![(y, x)];
+            let mut current = state;
+            while parents[current] != u32::MAX {
+                current = parents[current] as usize;
+                path.push(state_position(current));
+            }
+            path.push(rules.start);
+            path.reverse();
+            return Some((best_costs[state], path));
+        }
+        for (n_y, n_x, heat_loss, new_dir, new_run) in
+            grid.get_neighbours(y, x, Some(dir), run, rules)
+        {
+            let penalty = if new_dir != dir {
+                rules.turn_penalty
+            } else {
+                0
+            };
+            let new_cost = best_costs[state] + heat_loss + penalty;
+            let new_state = state_index((n_y, n_x), new_dir, new_run);
+            if new_cost < best_costs[new_state] {
+                best_costs[new_state] = new_cost;
+                parents[new_state] = state as u32;
+                open_states.push((new_cost + heuristic((n_y, n_x))) as usize, new_state);
+            }
+        }
+    }
+
+    None
 }
 
-fn calc_best_path(mut grid: ParseOutput, min_straights: i32, max_straights: i32) -> Solution {
+/// The original `BinaryHeap` and `HashMap` based search, unchanged, kept to compare
+/// against in the tests and benches. It only knows the default start, goal and run
+/// limits, with `straights` counted from 0 and `max_straights` exclusive.
+pub fn calc_best_path_heap(
+    grid: ParseOutput,
+    min_straights: i32,
+    max_straights: i32,
+) -> Solution {
     let mut memoization: HashMap<Cell, (u32, u32)> = HashMap::new();
     let mut open_fields = BinaryHeap::<Field>::new();
     let start = Field::new((0, 0), 0, 0, (0, 0), 0);
     let end_coords = (grid.fields.len() - 1, grid.fields[0].len() - 1);
     open_fields.push(start);
-    while let Some(mut current_field) = open_fields.pop() {
+    while let Some(current_field) = open_fields.pop() {
         let neighbours = grid.get_unmarked_neighbours(
             current_field.coordinate.0,
             current_field.coordinate.1,
@@ -113,6 +248,166 @@ mod tests {
         assert_eq!(part_2(parse_output), 94);
     }
 
+    #[test]
+    pub fn test_best_path() {
+        let parse_output = parse(TEST_INPUT);
+        let (heat_loss, path) =
+            calc_best_path(&parse_output, &MovementRules::crucible(), true).unwrap();
+        assert_eq!(heat_loss, 102);
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(12, 12)));
+        for w in path.windows(2) {
+            assert_eq!(w[0].0.abs_diff(w[1].0) + w[0].1.abs_diff(w[1].1), 1);
+        }
+        let path_heat_loss: Solution = path[1..]
+            .iter()
+            .map(|(y, x)| parse_output.get_field_cost(*y, *x))
+            .sum();
+        assert_eq!(path_heat_loss, heat_loss);
+    }
+
+    #[test]
+    pub fn test_no_path() {
+        let parse_output = parse("11111\n");
+        let rules = MovementRules::crucible();
+        assert_eq!(calc_best_path(&parse_output, &rules, true), None);
+        let rules = MovementRules {
+            max_run: 4,
+            ..MovementRules::crucible()
+        };
+        assert!(calc_best_path(&parse_output, &rules, true).is_some());
+    }
+
+    #[test]
+    pub fn test_start_and_goal() {
+        let parse_output = parse(TEST_INPUT);
+        let rules = MovementRules {
+            start: (12, 12),
+            goal: Some((0, 0)),
+            ..MovementRules::crucible()
+        };
+        let (_, path) = calc_best_path(&parse_output, &rules, true).unwrap();
+        assert_eq!((path[0], path[path.len() - 1]), ((12, 12), (0, 0)));
+        let rules = MovementRules {
+            goal: Some((0, 0)),
+            ..MovementRules::crucible()
+        };
+        assert_eq!(
+            calc_best_path(&parse_output, &rules, true),
+            Some((0, vec![(0, 0)]))
+        );
+    }
+
+    #[test]
+    pub fn test_off_grid_start_and_goal() {
+        let parse_output = parse(TEST_INPUT);
+        let off_grid_start = MovementRules {
+            start: (13, 0),
+            ..MovementRules::crucible()
+        };
+        let off_grid_goal = MovementRules {
+            goal: Some((0, 13)),
+            ..MovementRules::crucible()
+        };
+        for rules in [off_grid_start, off_grid_goal] {
+            assert_eq!(calc_best_path(&parse_output, &rules, true), None);
+        }
+    }
+
+    #[test]
+    pub fn test_reverse() {
+        // A single row can only be crossed by stepping back every 3 cells
+        let parse_output = parse("1111111\n");
+        assert_eq!(
+            calc_best_path(&parse_output, &MovementRules::crucible(), true),
+            None
+        );
+        let rules = MovementRules {
+            allow_reverse: true,
+            ..MovementRules::crucible()
+        };
+        let (cost, path) = calc_best_path(&parse_output, &rules, true).unwrap();
+        assert_eq!(cost, path.len() as Solution - 1);
+        assert!(path.windows(3).any(|w| w[0] == w[2]));
+    }
+
+    #[test]
+    pub fn test_turn_penalty() {
+        let parse_output = parse("1119\n9911\n");
+        assert_eq!(
+            calc_best_path(&parse_output, &MovementRules::crucible(), true)
+                .unwrap()
+                .0,
+            4
+        );
+        let rules = MovementRules {
+            turn_penalty: 10,
+            ..MovementRules::crucible()
+        };
+        assert_eq!(
+            calc_best_path(&parse_output, &rules, true),
+            Some((12 + 10, vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 3)]))
+        );
+    }
+
+    #[test]
+    pub fn test_diagonals() {
+        let parse_output = parse("199\n919\n991\n");
+        let rules = MovementRules {
+            diagonals: true,
+            ..MovementRules::crucible()
+        };
+        assert_eq!(
+            calc_best_path(&parse_output, &rules, true),
+            Some((2, vec![(0, 0), (1, 1), (2, 2)]))
+        );
+        assert_eq!(
+            calc_best_path(&parse_output, &MovementRules::crucible(), true)
+                .unwrap()
+                .0,
+            20
+        );
+    }
+
+    #[test]
+    pub fn test_matches_heap_search() {
+        let parse_output = parse(TEST_INPUT);
+        for (rules, (min_straights, max_straights)) in [
+            (MovementRules::crucible(), (0, 3)),
+            (MovementRules::ultra_crucible(), (3, 10)),
+        ] {
+            let expected = calc_best_path_heap(parse_output.clone(), min_straights, max_straights);
+            for use_heuristic in [false, true] {
+                let result = calc_best_path(&parse_output, &rules, use_heuristic);
+                assert_eq!(result.map(|(cost, _)| cost), Some(expected), "{:?}", rules);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_heuristic_matches_dijkstra() {
+        let parse_output = parse(TEST_INPUT);
+        let rule_sets = [
+            MovementRules {
+                allow_reverse: true,
+                turn_penalty: 4,
+                ..MovementRules::crucible()
+            },
+            MovementRules {
+                diagonals: true,
+                start: (3, 7),
+                goal: Some((12, 0)),
+                ..MovementRules::ultra_crucible()
+            },
+        ];
+        for rules in rule_sets {
+            let cost = |use_heuristic| {
+                calc_best_path(&parse_output, &rules, use_heuristic).map(|(cost, _)| cost)
+            };
+            assert_eq!(cost(true), cost(false), "{:?}", rules);
+        }
+    }
+
     #[bench]
     fn bench_parse(b: &mut Bencher) {
         b.iter(|| {
@@ -134,4 +429,32 @@ mod tests {
             assert_eq!(part_2(black_box(parse_output)), 1347);
         });
     }
+
+    #[bench]
+    fn bench_part_2_no_heuristic(b: &mut Bencher) {
+        let parse_output = parse(MAIN_INPUT);
+        let rules = MovementRules::ultra_crucible();
+        b.iter(|| {
+            let best_path = calc_best_path(black_box(&parse_output), &rules, false);
+            assert_eq!(best_path.unwrap().0, 1347);
+        });
+    }
+
+    #[bench]
+    fn bench_part_1_heap(b: &mut Bencher) {
+        let parse_output = parse(MAIN_INPUT);
+        b.iter(|| {
+            let best_path = calc_best_path_heap(black_box(&parse_output).clone(), 0, 3);
+            assert_eq!(best_path, 1195);
+        });
+    }
+
+    #[bench]
+    fn bench_part_2_heap(b: &mut Bencher) {
+        let parse_output = parse(MAIN_INPUT);
+        b.iter(|| {
+            let best_path = calc_best_path_heap(black_box(&parse_output).clone(), 3, 10);
+            assert_eq!(best_path, 1347);
+        });
+    }
 }
@@ -0,0 +1,126 @@
+use crate::Solution;
+
+pub type Direction = (i32, i32);
+
+/// The four straight directions first, so a state without diagonals only ever uses
+/// indices 0..4.
+pub const DIRECTIONS: [Direction; 8] = [
+    (-1, 0),
+    (0, 1),
+    (1, 0),
+    (0, -1),
+    (-1, 1),
+    (1, 1),
+    (1, -1),
+    (-1, -1),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MovementRules {
+    /// Cells the crucible has to move in one direction before it may turn or stop.
+    pub min_run: i32,
+    /// Cells the crucible may move in one direction at most.
+    pub max_run: i32,
+    /// Whether the crucible may turn around and go back the way it came.
+    pub allow_reverse: bool,
+    /// Added to the heat loss every time the direction changes.
+    pub turn_penalty: Solution,
+    pub diagonals: bool,
+    pub start: (usize, usize),
+    /// Bottom right if `None`.
+    pub goal: Option<(usize, usize)>,
+}
+
+impl Default for MovementRules {
+    fn default() -> Self {
+        MovementRules::crucible()
+    }
+}
+
+impl MovementRules {
+    pub fn crucible() -> MovementRules {
+        MovementRules {
+            min_run: 1,
+            max_run: 3,
+            allow_reverse: false,
+            turn_penalty: 0,
+            diagonals: false,
+            start: (0, 0),
+            goal: None,
+        }
+    }
+
+    pub fn ultra_crucible() -> MovementRules {
+        MovementRules {
+            min_run: 4,
+            max_run: 10,
+            ..MovementRules::crucible()
+        }
+    }
+
+    /// Index into `directions()`, `None` for the start which has no direction yet.
+    pub fn direction_index(&self, direction: Direction) -> Option<usize> {
+        self.directions().iter().position(|d| *d == direction)
+    }
+
+    pub fn directions(&self) -> &'static [Direction] {
+        if self.diagonals {
+            &DIRECTIONS
+        } else {
+            &DIRECTIONS[..4]
+        }
+    }
+
+    /// `None` unless at least one option is given. Options are `--ultra`, `--min-run=<n>`,
+    /// `--max-run=<n>`, `--reverse`, `--turn-penalty=<n>`, `--diagonals`, `--start=<y>,<x>`
+    /// and `--goal=<y>,<x>`.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Option<MovementRules> {
+        let mut rules = MovementRules::crucible();
+        let mut any = false;
+        let parse_cell = |cell: &str| {
+            let (y, x) = cell.split_once(',').unwrap();
+            (y.trim().parse().unwrap(), x.trim().parse().unwrap())
+        };
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("--min-run", n)) => rules.min_run = n.parse().unwrap(),
+                Some(("--max-run", n)) => rules.max_run = n.parse().unwrap(),
+                Some(("--turn-penalty", n)) => rules.turn_penalty = n.parse().unwrap(),
+                Some(("--start", cell)) => rules.start = parse_cell(cell),
+                Some(("--goal", cell)) => rules.goal = Some(parse_cell(cell)),
+                _ => match arg.as_str() {
+                    "--ultra" => {
+                        rules.min_run = 4;
+                        rules.max_run = 10;
+                    }
+                    "--reverse" => rules.allow_reverse = true,
+                    "--diagonals" => rules.diagonals = true,
+                    _ => continue,
+                },
+            }
+            any = true;
+        }
+        any.then_some(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_from_args() {
+        let args = ["--ultra", "--turn-penalty=2", "--diagonals", "--goal=3,4"];
+        let rules = MovementRules::from_args(args.iter().map(|a| a.to_string())).unwrap();
+        assert_eq!(
+            rules,
+            MovementRules {
+                turn_penalty: 2,
+                diagonals: true,
+                goal: Some((3, 4)),
+                ..MovementRules::ultra_crucible()
+            }
+        );
+        assert_eq!(MovementRules::from_args(std::iter::empty()), None);
+    }
+}
@@ -1,6 +1,7 @@
 #![feature(test)]
 
 use crate::Dir::*;
+use aoc2023::trace;
 use std::cmp::{max, min};
 use std::collections::{BTreeMap, HashMap};
 
@@ -90,7 +91,7 @@ fn part_1(instructions: &ParseOutput) -> Solution {
             _ => panic!(),
         }
     }
-    println!("{} {}", min_x, max_x);
+    trace!("{} {}", min_x, max_x);
     let mut inside_fields = 0;
     let mut outside_fields = 0;
     for (_, line) in grid.iter_mut() {
@@ -156,6 +157,7 @@ pub fn parse(file: &str) -> ParseOutput {
         .collect::<ParseOutput>()
 }
 fn main() {
+    aoc2023::trace::init();
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
@@ -1,18 +1,13 @@
 #![feature(test)]
 
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::ops::Div;
+use crate::tiles::TiledDistances;
+
+mod tiles;
 
-use fnv::{FnvHashMap, FnvHashSet};
 type Solution = usize;
 type Grid = Vec<Vec<u8>>;
 pub type ParseOutput = (Grid, (usize, usize));
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct Field(usize, usize, Solution);
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct FieldI(usize, usize, i32, i32, Solution);
 const MAIN_INPUT: &str = include_str!("main_input");
 
 pub fn parse(file: &'static str) -> ParseOutput {
@@ -33,162 +28,133 @@ pub fn parse(file: &'static str) -> ParseOutput {
 }
 
 fn part_1((grid, start): &ParseOutput, max_steps: usize) -> Solution {
-    let mut current_steps: BinaryHeap<Field> = BinaryHeap::new();
-    current_steps.push(Field(start.0, start.1, 0));
-    let is_even = !(max_steps % 2 == 0) as usize;
-    let mut fields_with_steps: FnvHashSet<(usize, usize)> = FnvHashSet::default();
-    let mut processed_paths: FnvHashSet<(usize, usize)> = FnvHashSet::default();
-    while let Some(Field(y, x, path_length)) = current_steps.pop() {
-        if !processed_paths.insert((y, x)) || path_length > max_steps {
-            continue;
-        }
-        if path_length % 2 == is_even {
-            fields_with_steps.insert((y, x));
-        }
-        for (n_y, n_x) in get_neighbours(grid, (y, x)) {
-            current_steps.push(Field(n_y, n_x, path_length + 1));
-        }
-    }
-    fields_with_steps.len()
+    reachable_counts(grid, *start, max_steps, false)[max_steps]
 }
 
-fn part_2(out: &ParseOutput, max_steps: usize) -> Solution {
-    let half_grid = out.0.len() / 2;
-    let full_grid = out.0.len();
-    let data_point_1 = (solve_i(out, half_grid), half_grid as f64);
-    let data_point_2 = (
-        solve_i(out, half_grid + full_grid * 2),
-        (half_grid + full_grid * 2) as f64,
-    );
-    let data_point_3 = (
-        solve_i(out, half_grid + full_grid * 4),
-        (half_grid + full_grid * 4) as f64,
-    );
-    lagrange_interpolate(
-        &[data_point_1, data_point_2, data_point_3],
-        max_steps as f64,
-    ) as Solution
-}
-
-fn lagrange_interpolate(points: &[(f64, f64)], x: f64) -> f64 {
-    let mut result = 0.0;
-
-    for i in 0..points.len() {
-        let mut li = 1.0;
-        for j in 0..points.len() {
-            if i != j {
-                li *= (x - points[j].1) / (points[i].1 - points[j].1);
-            }
-        }
-        result += points[i].0 * li;
-    }
+/// Periods the sampled sequence may take to settle into a quadratic, the example input
+/// needs one.
+const MAX_WARM_UP_PERIODS: usize = 4;
 
-    result
+fn part_2((grid, start): &ParseOutput, max_steps: usize) -> Solution {
+    TiledDistances::settled(grid, *start).count_reachable(max_steps)
 }
 
-fn solve_i((grid, start): &ParseOutput, max_steps: usize) -> f64 {
-    let is_even_c = !(max_steps % 2 == 0) as usize;
-    let mut current_steps: BinaryHeap<FieldI> = BinaryHeap::new();
-    current_steps.push(FieldI(start.0, start.1, 0, 0, 0));
-    let mut fields_with_steps: FnvHashMap<(usize, usize, i32, i32), usize> = FnvHashMap::default();
-    let mut processed_paths: FnvHashSet<(usize, usize, i32, i32)> = FnvHashSet::default();
-    while let Some(FieldI(y, x, i_y, i_x, path_length)) = current_steps.pop() {
-        if !processed_paths.insert((y, x, i_y, i_x)) || path_length > max_steps {
-            continue;
-        }
-        if path_length % 2 == is_even_c {
-            fields_with_steps.insert((y, x, i_y, i_x), path_length);
+/// Plots reachable within `max_steps` grow quadratically in the number of grid sizes walked
+/// once the frontier is far enough out. Samples every other grid size, so parity stays
+/// the same, and extrapolates from the first four samples that agree on a quadratic.
+/// Only works for square grids.
+pub fn part_2_extrapolated(out: &ParseOutput, max_steps: usize) -> Solution {
+    let period = out.0.len() * 2;
+    let offset = max_steps % period;
+    let target = max_steps / period;
+    for first in 0..=MAX_WARM_UP_PERIODS {
+        if first + 3 >= target {
+            return solve_i(out, max_steps);
         }
-        for (n_y, n_x, n_i_y, n_i_x) in get_neighbours_i(grid, (y, x, i_y, i_x)) {
-            current_steps.push(FieldI(n_y, n_x, n_i_y, n_i_x, path_length + 1));
+        let counts = reachable_counts(&out.0, out.1, offset + (first + 3) * period, true);
+        let window = [0, 1, 2, 3].map(|i| counts[offset + (first + i) * period]);
+        if let Some(result) = extrapolate_quadratic(window, target - first) {
+            return result;
         }
     }
-    fields_with_steps.len() as f64
+    panic!(
+        "Reachable plots don't grow quadratically after {} periods",
+        MAX_WARM_UP_PERIODS
+    );
 }
 
-fn get_neighbours_i(
-    grid: &Grid,
-    (y, x, i_y, i_x): (usize, usize, i32, i32),
-) -> Vec<(usize, usize, i32, i32)> {
-    let mut n = Vec::new();
-    match x.checked_sub(1) {
-        Some(n_x) => {
-            if grid[y][n_x] != b'#' {
-                n.push((y, n_x, i_y, i_x))
-            }
-        }
-        None => {
-            if grid[y][grid.len() - 1] != b'#' {
-                n.push((y, grid.len() - 1, i_y, i_x - 1))
-            }
-        }
-    }
-    match x + 1 < grid.len() {
-        true => {
-            if grid[y][x + 1] != b'#' {
-                n.push((y, x + 1, i_y, i_x))
-            }
-        }
-        false => {
-            if grid[y][0] != b'#' {
-                n.push((y, 0, i_y, i_x + 1))
-            }
-        }
-    }
-    match y.checked_sub(1) {
-        Some(n_y) => {
-            if grid[n_y][x] != b'#' {
-                n.push((n_y, x, i_y, i_x))
-            }
-        }
-        None => {
-            if grid[grid.len() - 1][x] != b'#' {
-                n.push((grid.len() - 1, x, i_y - 1, i_x))
-            }
-        }
-    }
-    match y + 1 < grid.len() {
-        true => {
-            if grid[y + 1][x] != b'#' {
-                n.push((y + 1, x, i_y, i_x))
-            }
-        }
-        false => {
-            if grid[0][x] != b'#' {
-                n.push((0, x, i_y + 1, i_x))
-            }
-        }
+/// Fits a quadratic through the samples at 0, 1 and 2 with exact finite differences and
+/// evaluates it at `n`. `None` if the sample at 3 is not on the same quadratic.
+pub fn extrapolate_quadratic(samples: [Solution; 4], n: usize) -> Option<Solution> {
+    let [f_0, f_1, f_2, f_3] = samples.map(|s| s as i128);
+    if f_3 - 3 * f_2 + 3 * f_1 - f_0 != 0 {
+        return None;
     }
+    let first_difference = f_1 - f_0;
+    let second_difference = f_2 - 2 * f_1 + f_0;
+    let n = n as i128;
+    let result = f_0 + n * first_difference + n * (n - 1) / 2 * second_difference;
+    Some(Solution::try_from(result).unwrap())
+}
 
-    n
+fn solve_i((grid, start): &ParseOutput, max_steps: usize) -> Solution {
+    reachable_counts(grid, *start, max_steps, true)[max_steps]
 }
 
-fn get_neighbours(grid: &Grid, (y, x): (usize, usize)) -> Vec<(usize, usize)> {
-    let mut n = Vec::new();
-    if let Some(n_x) = x.checked_sub(1) {
-        if grid[y][n_x] != b'#' {
-            n.push((y, n_x))
-        }
-    }
-    if x + 1 < grid.len() {
-        if grid[y][x + 1] != b'#' {
-            n.push((y, x + 1))
-        }
+/// `counts[n]` is the number of plots that can be reached in exactly `n` steps, for every
+/// `n` up to `max_steps`. Those are the plots first reached after `n`, `n - 2`, ... steps,
+/// since the rest of the steps can be spent walking back and forth.
+pub fn reachable_counts(
+    grid: &Grid,
+    start: (usize, usize),
+    max_steps: usize,
+    tiled: bool,
+) -> Vec<Solution> {
+    let layers = layer_sizes(grid, start, max_steps, tiled);
+    let mut counts: Vec<Solution> = Vec::with_capacity(layers.len());
+    for (n, layer) in layers.into_iter().enumerate() {
+        counts.push(layer + if n >= 2 { counts[n - 2] } else { 0 });
     }
+    counts
+}
 
-    if let Some(n_y) = y.checked_sub(1) {
-        if grid[n_y][x] != b'#' {
-            n.push((n_y, x))
+/// Plots first reached after exactly `n` steps, for every `n` up to `max_steps`. Walks one
+/// frontier at a time over a dense visited array. With `tiled` the grid repeats in every
+/// direction and the array covers everything `max_steps` away from the start, otherwise
+/// the grid ends at its edges.
+fn layer_sizes(grid: &Grid, start: (usize, usize), max_steps: usize, tiled: bool) -> Vec<Solution> {
+    let (height, width) = (grid.len(), grid[0].len());
+    let (block_height, block_width, origin) = if tiled {
+        let origin = (
+            start.0 as isize - max_steps as isize,
+            start.1 as isize - max_steps as isize,
+        );
+        (2 * max_steps + 1, 2 * max_steps + 1, origin)
+    } else {
+        (height, width, (0, 0))
+    };
+    let is_plot = |y: usize, x: usize| {
+        let grid_y = (y as isize + origin.0).rem_euclid(height as isize) as usize;
+        let grid_x = (x as isize + origin.1).rem_euclid(width as isize) as usize;
+        grid[grid_y][grid_x] != b'#'
+    };
+    let block_start = (
+        (start.0 as isize - origin.0) as usize,
+        (start.1 as isize - origin.1) as usize,
+    );
+    let mut visited = vec![false; block_height * block_width];
+    visited[block_start.0 * block_width + block_start.1] = true;
+    let mut layers = vec![0; max_steps + 1];
+    let mut frontier = vec![block_start];
+    let mut next_frontier = Vec::new();
+    for layer in layers.iter_mut() {
+        if frontier.is_empty() {
+            break;
         }
-    }
-    if y + 1 < grid.len() {
-        if grid[y + 1][x] != b'#' {
-            n.push((y + 1, x))
+        *layer = frontier.len();
+        for (y, x) in frontier.drain(..) {
+            let neighbours = [
+                (y.wrapping_sub(1), x),
+                (y + 1, x),
+                (y, x.wrapping_sub(1)),
+                (y, x + 1),
+            ];
+            for (n_y, n_x) in neighbours {
+                if n_y >= block_height || n_x >= block_width {
+                    continue;
+                }
+                let index = n_y * block_width + n_x;
+                if !visited[index] && is_plot(n_y, n_x) {
+                    visited[index] = true;
+                    next_frontier.push((n_y, n_x));
+                }
+            }
         }
+        std::mem::swap(&mut frontier, &mut next_frontier);
     }
-
-    n
+    layers
 }
+
 fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output, 64));
@@ -217,14 +183,51 @@ mod tests {
     #[test]
     pub fn test_part_2() {
         let parse_output = parse(TEST_INPUT);
-        assert_eq!(solve_i(&parse_output, 10), 50.0);
-        assert_eq!(solve_i(&parse_output, 50), 1594.0);
+        assert_eq!(solve_i(&parse_output, 10), 50);
+        assert_eq!(solve_i(&parse_output, 50), 1594);
         assert_eq!(part_2(&parse_output, 10), 50);
         assert_eq!(part_2(&parse_output, 50), 1594);
         assert_eq!(part_2(&parse_output, 100), 6536);
         assert_eq!(part_2(&parse_output, 500), 167004);
         assert_eq!(part_2(&parse_output, 1000), 668697);
         assert_eq!(part_2(&parse_output, 5000), 16733044);
+        for steps in [6, 10, 50, 100, 500, 1000, 5000] {
+            assert_eq!(
+                part_2_extrapolated(&parse_output, steps),
+                part_2(&parse_output, steps)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_extrapolate_quadratic() {
+        // n^2 + 3n + 7
+        assert_eq!(extrapolate_quadratic([7, 11, 17, 25], 3), Some(25));
+        assert_eq!(
+            extrapolate_quadratic([7, 11, 17, 25], 202300),
+            Some(202300 * 202300 + 3 * 202300 + 7)
+        );
+        // Beyond 2^53 f64 can no longer tell neighbouring integers apart
+        let n = 100_000_000;
+        assert_eq!(
+            extrapolate_quadratic([7, 11, 17, 25], n),
+            Some(n * n + 3 * n + 7)
+        );
+        assert_eq!(extrapolate_quadratic([7, 11, 17, 26], 4), None);
+    }
+
+    #[test]
+    pub fn test_reachable_counts() {
+        let (grid, start) = parse(TEST_INPUT);
+        assert_eq!(
+            reachable_counts(&grid, start, 6, false),
+            vec![1, 2, 4, 6, 9, 13, 16]
+        );
+        let counts = reachable_counts(&grid, start, 500, true);
+        assert_eq!(
+            [6, 10, 50, 100, 500].map(|n| counts[n]),
+            [16, 50, 1594, 6536, 167004]
+        );
     }
 
     #[bench]
@@ -238,71 +241,26 @@ mod tests {
     fn bench_part_1(b: &mut Bencher) {
         let parse_output = parse(MAIN_INPUT);
         b.iter(move || {
-            assert_eq!(part_1(black_box(&parse_output), 64), 1798691765);
+            assert_eq!(part_1(black_box(&parse_output), 64), 3646);
         });
     }
 
     #[bench]
-    fn bench_part_2(b: &mut Bencher) {
-        let parse_output = &mut parse(MAIN_INPUT);
+    fn bench_part_2_extrapolated(b: &mut Bencher) {
+        let parse_output = parse(MAIN_INPUT);
         b.iter(|| {
-            assert_eq!(part_2(black_box(parse_output), 26501365), 1104);
+            assert_eq!(
+                part_2_extrapolated(black_box(&parse_output), 26501365),
+                606188414811259
+            );
         });
     }
-}
-
-impl PartialOrd for FieldI {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.4 > other.4 {
-            return Some(Ordering::Less);
-        }
-
-        if self.4 == other.4 {
-            return Some(Ordering::Equal);
-        }
-
-        return Some(Ordering::Greater);
-    }
-}
-
-impl Ord for FieldI {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.4 > other.4 {
-            return Ordering::Less;
-        }
 
-        if self.4 == other.4 {
-            return Ordering::Equal;
-        }
-
-        return Ordering::Greater;
-    }
-}
-
-impl PartialOrd for Field {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.2 > other.2 {
-            return Some(Ordering::Less);
-        }
-
-        if self.2 == other.2 {
-            return Some(Ordering::Equal);
-        }
-
-        return Some(Ordering::Greater);
-    }
-}
-
-impl Ord for Field {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.2 > other.2 {
-            return Ordering::Less;
-        }
-
-        if self.2 == other.2 {
-            return Ordering::Equal;
-        }
-
-        return Ordering::Greater;
+    #[bench]
+    fn bench_part_2(b: &mut Bencher) {
+        let parse_output = &mut parse(MAIN_INPUT);
+        b.iter(|| {
+            assert_eq!(part_2(black_box(parse_output), 26501365), 606188414811259);
+        });
     }
 }
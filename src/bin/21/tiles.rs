@@ -0,0 +1,207 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{Grid, Solution};
+
+const UNREACHABLE: usize = usize::MAX;
+/// Largest ring of tiles `TiledDistances::settled` tries before giving up.
+const MAX_RADIUS: usize = 8;
+
+/// BFS distances from the start to every cell of a block of `2 * radius + 1` tiles in both
+/// directions around the start tile.
+///
+/// Far enough out, walking one more tile in a straight line costs exactly the tile height
+/// or width. Then every tile beyond the block has the distances of an outermost tile plus a
+/// multiple of the tile size, so the outermost edge and corner tiles stand for all tiles
+/// behind them. `is_periodic` checks that between the two outermost rings.
+#[derive(Debug, Clone)]
+pub struct TiledDistances {
+    distances: Vec<usize>,
+    height: usize,
+    width: usize,
+    radius: usize,
+}
+
+impl TiledDistances {
+    pub fn new(grid: &Grid, start: (usize, usize), radius: usize) -> TiledDistances {
+        let (height, width) = (grid.len(), grid[0].len());
+        let tiles = 2 * radius + 1;
+        let (block_height, block_width) = (tiles * height, tiles * width);
+        let mut distances = vec![UNREACHABLE; block_height * block_width];
+        let block_start = (start.0 + radius * height, start.1 + radius * width);
+        distances[block_start.0 * block_width + block_start.1] = 0;
+        let mut open_fields = VecDeque::from([block_start]);
+        while let Some((y, x)) = open_fields.pop_front() {
+            let distance = distances[y * block_width + x];
+            let neighbours = [
+                (y.wrapping_sub(1), x),
+                (y + 1, x),
+                (y, x.wrapping_sub(1)),
+                (y, x + 1),
+            ];
+            for (n_y, n_x) in neighbours {
+                if n_y >= block_height || n_x >= block_width {
+                    continue;
+                }
+                let index = n_y * block_width + n_x;
+                if grid[n_y % height][n_x % width] == b'#' || distances[index] != UNREACHABLE {
+                    continue;
+                }
+                distances[index] = distance + 1;
+                open_fields.push_back((n_y, n_x));
+            }
+        }
+        TiledDistances {
+            distances,
+            height,
+            width,
+            radius,
+        }
+    }
+
+    /// Grows the block until the distances repeat, see `is_periodic`.
+    pub fn settled(grid: &Grid, start: (usize, usize)) -> TiledDistances {
+        (2..=MAX_RADIUS)
+            .map(|radius| TiledDistances::new(grid, start, radius))
+            .find(|tiles| tiles.is_periodic())
+            .unwrap_or_else(|| panic!("Distances don't repeat within {} tiles", MAX_RADIUS))
+    }
+
+    /// Distance to cell `(y, x)` of the tile `(t_y, t_x)` tiles away from the start tile.
+    pub fn distance(&self, (t_y, t_x): (i64, i64), (y, x): (usize, usize)) -> Option<usize> {
+        let tiles = 2 * self.radius + 1;
+        let block_y = (t_y + self.radius as i64) as usize * self.height + y;
+        let block_x = (t_x + self.radius as i64) as usize * self.width + x;
+        let distance = self.distances[block_y * tiles * self.width + block_x];
+        (distance != UNREACHABLE).then_some(distance)
+    }
+
+    /// Whether every outermost tile is exactly one tile size further away than its inner
+    /// neighbour, for every cell.
+    pub fn is_periodic(&self) -> bool {
+        let r = self.radius as i64;
+        let (height, width) = (self.height, self.width);
+        let step_matches = |outer: (i64, i64), inner: (i64, i64), step: usize, cell| match (
+            self.distance(outer, cell),
+            self.distance(inner, cell),
+        ) {
+            (None, None) => true,
+            (Some(outer), Some(inner)) => outer == inner + step,
+            _ => false,
+        };
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .all(|cell| {
+                let edges_match = (-r + 1..r).all(|t| {
+                    step_matches((r, t), (r - 1, t), height, cell)
+                        && step_matches((-r, t), (-r + 1, t), height, cell)
+                        && step_matches((t, r), (t, r - 1), width, cell)
+                        && step_matches((t, -r), (t, -r + 1), width, cell)
+                });
+                let corners_match =
+                    [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+                        .into_iter()
+                        .all(|(s_y, s_x)| {
+                            let corner = (s_y * r, s_x * r);
+                            step_matches(corner, (s_y * (r - 1), s_x * r), height, cell)
+                                && step_matches(corner, (s_y * r, s_x * (r - 1)), width, cell)
+                        });
+                edges_match && corners_match
+            })
+    }
+
+    /// Plots that can be reached in exactly `steps` steps on the infinitely tiled grid.
+    pub fn count_reachable(&self, steps: usize) -> Solution {
+        let r = self.radius as i64;
+        let mut corner_counts: HashMap<usize, Solution> = HashMap::new();
+        let mut reachable = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for t_y in -r..=r {
+                    for t_x in -r..=r {
+                        let distance = match self.distance((t_y, t_x), (y, x)) {
+                            Some(distance) if distance <= steps => distance,
+                            _ => continue,
+                        };
+                        let left = steps - distance;
+                        reachable += match (t_y.abs() == r, t_x.abs() == r) {
+                            (false, false) => left.is_multiple_of(2) as Solution,
+                            (true, false) => count_in_line(left, self.height),
+                            (false, true) => count_in_line(left, self.width),
+                            (true, true) => *corner_counts.entry(left).or_insert_with(|| {
+                                (0..=left / self.height)
+                                    .map(|i| count_in_line(left - i * self.height, self.width))
+                                    .sum()
+                            }),
+                        };
+                    }
+                }
+            }
+        }
+        reachable
+    }
+}
+
+/// Tiles `k = 0, 1, ...` further out that leave an even number of the `left` steps to walk
+/// back and forth, when every tile costs `tile_size` more steps.
+fn count_in_line(left: usize, tile_size: usize) -> Solution {
+    let tiles = left / tile_size;
+    if tile_size.is_multiple_of(2) {
+        return if left.is_multiple_of(2) { tiles + 1 } else { 0 };
+    }
+    let first = left % 2;
+    if first > tiles {
+        0
+    } else {
+        (tiles - first) / 2 + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, solve_i};
+
+    #[test]
+    pub fn test_count_in_line() {
+        // Only tile 1 leaves an even number of the 7 steps, 4
+        assert_eq!(count_in_line(7, 3), 1);
+        assert_eq!(count_in_line(9, 3), 2);
+        assert_eq!(count_in_line(8, 4), 3);
+        assert_eq!(count_in_line(7, 4), 0);
+    }
+
+    #[test]
+    pub fn test_rectangular_off_center() {
+        let grid: Grid = ["..#....", "S......", ".#...#.", "...##..", ".#....."]
+            .iter()
+            .map(|l| l.as_bytes().to_vec())
+            .collect();
+        let parse_output = (grid, (1, 0));
+        let tiles = TiledDistances::settled(&parse_output.0, parse_output.1);
+        for steps in [0, 1, 2, 7, 10, 25, 40, 41, 64] {
+            assert_eq!(
+                tiles.count_reachable(steps),
+                solve_i(&parse_output, steps),
+                "{} steps",
+                steps
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_not_periodic() {
+        // Without a free row, going diagonally across tiles beats going one axis at a time
+        let grid: Grid = ["..#....", "S...#..", ".#.....", "...##.."]
+            .iter()
+            .map(|l| l.as_bytes().to_vec())
+            .collect();
+        assert!(!TiledDistances::new(&grid, (1, 0), 4).is_periodic());
+    }
+
+    #[test]
+    pub fn test_needs_larger_block() {
+        let (grid, start) = parse(include_str!("test_input"));
+        assert!(!TiledDistances::new(&grid, start, 3).is_periodic());
+        assert_eq!(TiledDistances::settled(&grid, start).radius, 4);
+    }
+}
@@ -0,0 +1,111 @@
+/// Dominator tree of a DAG rooted at node 0. A node dominates another if every path from
+/// the root to the other one goes through it.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    subtree_sizes: Vec<usize>,
+}
+
+impl DominatorTree {
+    /// `predecessors[v]` are the nodes with an edge to `v`. Nodes have to be in topological
+    /// order, so all predecessors of `v` are smaller than `v`, and every node but the root
+    /// needs at least one predecessor.
+    ///
+    /// In a DAG the immediate dominator of a node is the lowest common ancestor of its
+    /// predecessors in the dominator tree built so far. Ancestors are found by binary
+    /// lifting, which makes this O((nodes + edges) log nodes).
+    pub fn from_dag(predecessors: &[Vec<usize>]) -> DominatorTree {
+        let n = predecessors.len();
+        let levels = (usize::BITS - n.leading_zeros()).max(1) as usize;
+        let mut ancestors = vec![vec![0; n]; levels];
+        let mut depths = vec![0; n];
+        for v in 1..n {
+            let mut parents = predecessors[v].iter();
+            let first = *parents
+                .next()
+                .expect("Every node but the root needs a predecessor");
+            assert!(
+                predecessors[v].iter().all(|p| *p < v),
+                "Nodes are not in topological order"
+            );
+            let dominator = parents.fold(first, |dominator, p| {
+                lowest_common_ancestor(&ancestors, &depths, dominator, *p)
+            });
+            depths[v] = depths[dominator] + 1;
+            ancestors[0][v] = dominator;
+            for level in 1..levels {
+                ancestors[level][v] = ancestors[level - 1][ancestors[level - 1][v]];
+            }
+        }
+        let immediate_dominators = ancestors.swap_remove(0);
+        let mut subtree_sizes = vec![1; n];
+        for v in (1..n).rev() {
+            subtree_sizes[immediate_dominators[v]] += subtree_sizes[v];
+        }
+        DominatorTree { subtree_sizes }
+    }
+
+    /// Number of nodes `v` dominates, itself included.
+    pub fn subtree_size(&self, v: usize) -> usize {
+        self.subtree_sizes[v]
+    }
+}
+
+/// `ancestors[level][v]` is the ancestor `2^level` levels above `v`.
+fn lowest_common_ancestor(
+    ancestors: &[Vec<usize>],
+    depths: &[usize],
+    mut a: usize,
+    mut b: usize,
+) -> usize {
+    if depths[a] < depths[b] {
+        std::mem::swap(&mut a, &mut b);
+    }
+    for level in (0..ancestors.len()).rev() {
+        if depths[a] - depths[b] >= 1 << level {
+            a = ancestors[level][a];
+        }
+    }
+    if a == b {
+        return a;
+    }
+    for level in (0..ancestors.len()).rev() {
+        if ancestors[level][a] != ancestors[level][b] {
+            a = ancestors[level][a];
+            b = ancestors[level][b];
+        }
+    }
+    ancestors[0][a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_diamond_and_chain() {
+        // 0 -> 1 -> 2, 1 -> 3, 2 -> 4, 3 -> 4, 4 -> 6, 0 -> 5 -> 6
+        let predecessors = vec![
+            vec![],
+            vec![0],
+            vec![1],
+            vec![1],
+            vec![2, 3],
+            vec![0],
+            vec![4, 5],
+        ];
+        let tree = DominatorTree::from_dag(&predecessors);
+        let sizes: Vec<usize> = (0..7).map(|v| tree.subtree_size(v)).collect();
+        assert_eq!(sizes, vec![7, 4, 1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    pub fn test_long_chain() {
+        let n = 1000;
+        let predecessors: Vec<Vec<usize>> = (0..n)
+            .map(|v| if v == 0 { vec![] } else { vec![v - 1] })
+            .collect();
+        let tree = DominatorTree::from_dag(&predecessors);
+        assert_eq!(tree.subtree_size(0), n);
+        assert_eq!(tree.subtree_size(n - 10), 10);
+    }
+}
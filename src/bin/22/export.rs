@@ -0,0 +1,111 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use fnv::FnvHashSet;
+
+use crate::stack::BrickStack;
+use crate::BrickID;
+
+/// Corners of a unit cube as (x, y, z). The faces below index into it counter-clockwise
+/// seen from outside once y and z are swapped for the OBJ file.
+const CORNERS: [(usize, usize, usize); 8] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (1, 1, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (1, 1, 1),
+    (0, 1, 1),
+];
+const FACES: [[usize; 4]; 6] = [
+    [1, 2, 3, 0],
+    [7, 6, 5, 4],
+    [4, 5, 1, 0],
+    [6, 7, 3, 2],
+    [3, 7, 4, 0],
+    [5, 6, 2, 1],
+];
+
+/// Writes the bricks as cuboids to an OBJ file and their colours to an MTL file next to it.
+/// Every brick is its own object. Bricks that can be disintegrated safely are bright yellow,
+/// the others get muted colours that differ from brick to brick.
+pub fn write_obj(stack: &BrickStack, safe: &FnvHashSet<BrickID>, path: &Path) -> io::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path.file_name().unwrap().to_string_lossy();
+    let (obj, mtl) = to_obj(stack, safe, &mtl_name);
+    fs::write(path, obj)?;
+    fs::write(mtl_path, mtl)
+}
+
+/// The OBJ and MTL file contents. OBJ is y-up, so the puzzle's z becomes y.
+pub fn to_obj(stack: &BrickStack, safe: &FnvHashSet<BrickID>, mtl_name: &str) -> (String, String) {
+    let mut obj = format!("mtllib {}\n", mtl_name);
+    let mut mtl = String::from("newmtl safe\nKd 1.000 0.850 0.100\n");
+    let mut vertices = 0;
+    for (id, ((s_z, s_y, s_x), (e_z, e_y, e_x))) in stack.bricks() {
+        writeln!(obj, "o brick_{}", id).unwrap();
+        for (c_x, c_y, c_z) in CORNERS {
+            let x = if c_x == 0 { *s_x } else { e_x + 1 };
+            let y = if c_y == 0 { *s_y } else { e_y + 1 };
+            let z = if c_z == 0 { *s_z } else { e_z + 1 };
+            writeln!(obj, "v {} {} {}", x, z, y).unwrap();
+        }
+        if safe.contains(&id) {
+            obj.push_str("usemtl safe\n");
+        } else {
+            let (r, g, b) = brick_colour(id);
+            writeln!(mtl, "newmtl brick_{}\nKd {:.3} {:.3} {:.3}", id, r, g, b).unwrap();
+            writeln!(obj, "usemtl brick_{}", id).unwrap();
+        }
+        for face in FACES {
+            let [a, b, c, d] = face.map(|corner| vertices + corner + 1);
+            writeln!(obj, "f {} {} {} {}", a, b, c, d).unwrap();
+        }
+        vertices += CORNERS.len();
+    }
+    (obj, mtl)
+}
+
+/// Hues spread by the golden ratio so neighbouring ids look different, kept darker than
+/// the safe bricks.
+fn brick_colour(id: BrickID) -> (f64, f64, f64) {
+    let hue = (id as f64 * 0.618_034).fract() * 6.0;
+    let (saturation, value) = (0.5, 0.6);
+    let chroma = value * saturation;
+    let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let m = value - chroma;
+    (r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, solve};
+
+    #[test]
+    pub fn test_obj() {
+        let (safe, stack) = solve(&parse(include_str!("test_input")));
+        let (obj, mtl) = to_obj(&stack, &safe, "bricks.mtl");
+        let count = |prefix: &str| obj.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!((count("o "), count("v "), count("f ")), (7, 56, 42));
+        assert_eq!(obj.matches("usemtl safe").count(), 5);
+        assert_eq!(mtl.matches("newmtl").count(), 3);
+        // Brick A spans x 1, y 0 to 2 on the ground
+        let brick_a: Vec<&str> = obj.lines().skip(2).take(8).collect();
+        assert_eq!(brick_a[0], "v 1 1 0");
+        assert_eq!(brick_a[6], "v 2 2 3");
+        let last_face = obj.lines().last().unwrap();
+        assert_eq!(last_face, "f 54 55 51 50");
+    }
+}
@@ -1,13 +1,21 @@
 #![feature(test)]
 
-use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::iter;
+use std::path::PathBuf;
 
-use fnv::{FnvHashMap, FnvHashSet};
+use fnv::FnvHashSet;
+
+use crate::dominators::DominatorTree;
+use crate::stack::BrickStack;
+
+mod dominators;
+mod export;
+pub mod stack;
 
 type Solution = usize;
 
 type BrickID = usize;
-pub type BrickGrid = FnvHashMap<(usize, usize), HashSet<BrickID>>;
 pub type Brick = ((usize, usize, usize), (usize, usize, usize));
 pub type ParseOutput = Vec<Brick>;
 const MAIN_INPUT: &str = include_str!("main_input");
@@ -38,133 +46,84 @@ pub fn parse(file: &str) -> ParseOutput {
 }
 
 fn part_1(bricks: &ParseOutput) -> Solution {
-    let (deleted_bricks, _, _) = solve(bricks);
-    deleted_bricks.len()
+    chain_reaction_sizes(bricks)
+        .into_iter()
+        .filter(|falling| *falling == 0)
+        .count()
 }
 
 fn part_2(bricks: &mut ParseOutput) -> Solution {
-    let (deleted_bricks, support, supported_by) = solve(bricks);
-    let remaining_bricks = (0..bricks.len())
-        .filter(|d| !deleted_bricks.contains(d))
-        .collect::<FnvHashSet<usize>>();
-    remaining_bricks
-        .iter()
-        .map(|b| {
-            let mut falling = FnvHashSet::default();
-            let mut bricks: BTreeSet<&usize> = BTreeSet::new();
-            bricks.insert(b);
-            while let Some(brick) = bricks.pop_first() {
-                for brick_this_one_supports in support.get(brick).unwrap() {
-                    let supported_by_no_others = supported_by
-                        .get(brick_this_one_supports)
-                        .unwrap()
-                        .iter()
-                        .filter(|os| **os != *brick && !falling.contains(*os))
-                        .count()
-                        == 0;
-                    if supported_by_no_others {
-                        bricks.insert(brick_this_one_supports);
-                        falling.insert(*brick_this_one_supports);
-                    }
-                }
-            }
-            falling.len()
-        })
-        .sum()
+    chain_reaction_sizes(bricks).into_iter().sum()
 }
-fn solve(
-    bricks: &ParseOutput,
-) -> (
-    FnvHashSet<usize>,
-    FnvHashMap<usize, FnvHashSet<usize>>,
-    FnvHashMap<usize, FnvHashSet<usize>>,
-) {
-    let mut brick_grid: BrickGrid = FnvHashMap::default();
-    let mut br: Vec<Brick> = bricks.clone();
-    for (i, ((_, s_y, s_x), (_, e_y, e_x))) in bricks.iter().enumerate() {
-        for y in *s_y..=*e_y {
-            for x in *s_x..=*e_x {
-                brick_grid.entry((y, x)).or_default().insert(i);
+
+/// How many other bricks fall when each brick is disintegrated. A brick falls exactly when
+/// every chain of supports from the ground up to it goes through the removed brick, that
+/// is when the removed brick dominates it in the support graph rooted at the ground.
+pub fn chain_reaction_sizes(bricks: &ParseOutput) -> Vec<Solution> {
+    let (_, stack) = solve(bricks);
+    let mut missing_supports: Vec<usize> = (0..bricks.len())
+        .map(|b| stack.supported_by(b).len())
+        .collect();
+    let mut bottom_up: Vec<BrickID> = (0..bricks.len())
+        .filter(|b| missing_supports[*b] == 0)
+        .collect();
+    let mut i = 0;
+    while i < bottom_up.len() {
+        for supported in stack.supports(bottom_up[i]) {
+            missing_supports[*supported] -= 1;
+            if missing_supports[*supported] == 0 {
+                bottom_up.push(*supported);
             }
         }
+        i += 1;
     }
-    let mut supports_map: FnvHashMap<usize, FnvHashSet<usize>> = FnvHashMap::default();
-    let mut supported_by_map: FnvHashMap<usize, FnvHashSet<usize>> = FnvHashMap::default();
-    for i in 0..br.len() {
-        supports_map.insert(i, FnvHashSet::default());
-        supported_by_map.insert(i, FnvHashSet::default());
-        let ((s_z, s_y, s_x), (_, e_y, e_x)) = br.get(i).unwrap();
-        let mut new_brick_min = *s_z;
-        'outer: while new_brick_min > 1 {
-            for y in *s_y..=*e_y {
-                for x in *s_x..=*e_x {
-                    let bricks_in_z = brick_grid.get(&(y, x)).unwrap();
-                    for brick_id in bricks_in_z {
-                        if *brick_id != i {
-                            let ((_, _, _), (other_brick_max_z, _, _)) =
-                                br.get(*brick_id).unwrap() as &Brick;
-                            if new_brick_min - 1 == *other_brick_max_z {
-                                break 'outer;
-                            }
-                        }
-                    }
-                }
-            }
-            new_brick_min -= 1;
-        }
-
-        let brick = br.get_mut(i).unwrap();
-        let d = brick.1 .0 - brick.0 .0;
-        brick.0 .0 = new_brick_min;
-        brick.1 .0 = new_brick_min + d;
+    // Node 0 is the ground
+    let mut nodes = vec![0; bricks.len()];
+    for (i, brick) in bottom_up.iter().enumerate() {
+        nodes[*brick] = i + 1;
     }
-
-    for i in 0..br.len() {
-        let ((brick_min_z, s_y, s_x), (_, e_y, e_x)) = br.get(i).unwrap();
-        for y in *s_y..=*e_y {
-            for x in *s_x..=*e_x {
-                let bricks_in_z = brick_grid.get(&(y, x)).unwrap();
-                for brick_id in bricks_in_z {
-                    if *brick_id != i {
-                        let ((_, _, _), (other_brick_max_z, _, _)) =
-                            br.get(*brick_id).unwrap() as &Brick;
-                        if brick_min_z - 1 == *other_brick_max_z {
-                            supported_by_map.get_mut(&i).unwrap().insert(*brick_id);
-                            supports_map.get_mut(brick_id).unwrap().insert(i);
-                        }
-                    }
-                }
+    let predecessors: Vec<Vec<usize>> = iter::once(Vec::new())
+        .chain(bottom_up.iter().map(|brick| {
+            let supporting = stack.supported_by(*brick);
+            if supporting.is_empty() {
+                vec![0]
+            } else {
+                supporting.iter().map(|s| nodes[*s]).collect()
             }
-        }
-    }
+        }))
+        .collect();
+    let tree = DominatorTree::from_dag(&predecessors);
+    (0..bricks.len())
+        .map(|b| tree.subtree_size(nodes[b]) - 1)
+        .collect()
+}
 
-    let mut deleted_brick_ids: FnvHashSet<usize> = FnvHashSet::default();
-    for i in 0..br.len() {
-        let bricks_this_one_is_supporting = supports_map.get(&i).unwrap();
-        if bricks_this_one_is_supporting.is_empty() {
-            deleted_brick_ids.insert(i);
-        } else {
-            let mut is_redundant = bricks_this_one_is_supporting.iter().all(|s| {
-                let bricks_this_one_is_supporting_are_supported_by =
-                    supported_by_map.get(s).unwrap();
-                let supports_n = bricks_this_one_is_supporting_are_supported_by
-                    .iter()
-                    .filter(|os| **os != i)
-                    .count();
-                supports_n > 0
-            });
-
-            if is_redundant {
-                deleted_brick_ids.insert(i);
-            }
-        }
-    }
-    (deleted_brick_ids, supports_map, supported_by_map)
+/// Settles the bricks and finds the ones that can be disintegrated without anything
+/// falling.
+fn solve(bricks: &ParseOutput) -> (FnvHashSet<BrickID>, BrickStack) {
+    let stack = BrickStack::settle(bricks);
+    let deleted_brick_ids = stack
+        .bricks()
+        .map(|(i, _)| i)
+        .filter(|i| {
+            stack
+                .supports(*i)
+                .iter()
+                .all(|s| stack.supported_by(*s).len() > 1)
+        })
+        .collect();
+    (deleted_brick_ids, stack)
 }
 fn main() {
     let parse_output = &mut parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
+    let export_path = env::args().find_map(|a| a.strip_prefix("--export=").map(PathBuf::from));
+    if let Some(path) = export_path {
+        let (safe_bricks, stack) = solve(parse_output);
+        export::write_obj(&stack, &safe_bricks, &path).unwrap();
+        println!("Wrote the settled bricks to {}", path.display());
+    }
 }
 
 #[cfg(test)]
@@ -182,6 +141,19 @@ mod tests {
         assert_eq!(part_1(&parse_output), 5);
     }
 
+    #[test]
+    pub fn test_chain_reaction_sizes() {
+        let parse_output = parse(TEST_INPUT);
+        assert_eq!(
+            chain_reaction_sizes(&parse_output),
+            vec![6, 0, 0, 0, 0, 1, 0]
+        );
+        let parse_output = parse(TEST_INPUT_2);
+        let (safe_bricks, _) = solve(&parse_output);
+        let sizes = chain_reaction_sizes(&parse_output);
+        assert!((0..sizes.len()).all(|b| safe_bricks.contains(&b) == (sizes[b] == 0)));
+    }
+
     #[test]
     pub fn test_part_lypheo() {
         let parse_output = &mut parse(TEST_INPUT_2);
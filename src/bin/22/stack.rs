@@ -0,0 +1,307 @@
+use std::iter;
+
+use crate::{Brick, BrickID};
+
+/// Settled bricks with the top z and top brick of every (y, x) column, so a dropped brick
+/// lands in one step on the highest top under it. Brick ids stay the same when other bricks
+/// are removed.
+#[derive(Debug, Clone, Default)]
+pub struct BrickStack {
+    bricks: Vec<Option<Brick>>,
+    supported_by: Vec<Vec<BrickID>>,
+    supports: Vec<Vec<BrickID>>,
+    /// Indexed `y * width + x`, grows when a brick reaches past the current extent.
+    heights: Vec<Option<(usize, BrickID)>>,
+    width: usize,
+}
+
+impl BrickStack {
+    pub fn new() -> BrickStack {
+        BrickStack::default()
+    }
+
+    /// Drops the bricks in order of their lowest z, so ids are indices into `bricks` sorted
+    /// that way.
+    pub fn settle(bricks: &[Brick]) -> BrickStack {
+        let mut order: Vec<&Brick> = bricks.iter().collect();
+        order.sort_by_key(|((s_z, _, _), _)| *s_z);
+        let mut stack = BrickStack::new();
+        let max_y = bricks
+            .iter()
+            .map(|(_, (_, e_y, _))| *e_y)
+            .max()
+            .unwrap_or(0);
+        let max_x = bricks
+            .iter()
+            .map(|(_, (_, _, e_x))| *e_x)
+            .max()
+            .unwrap_or(0);
+        stack.grow(max_y, max_x);
+        for brick in order {
+            stack.insert(*brick);
+        }
+        stack
+    }
+
+    /// Drops the brick from above the stack until it lands and returns its id.
+    pub fn insert(&mut self, brick: Brick) -> BrickID {
+        let id = self.bricks.len();
+        self.bricks.push(None);
+        self.supported_by.push(Vec::new());
+        self.supports.push(Vec::new());
+        let (_, (_, e_y, e_x)) = brick;
+        self.grow(e_y, e_x);
+        self.drop_brick(id, brick);
+        id
+    }
+
+    /// Removes the brick, lets everything it held up fall as far as it can and returns the
+    /// ids of the bricks that moved. Only the bricks that rest on it, directly or through
+    /// others, are dropped again.
+    pub fn remove(&mut self, id: BrickID) -> Vec<BrickID> {
+        let mut above = Vec::new();
+        let mut seen = vec![false; self.bricks.len()];
+        let mut queue = vec![id];
+        while let Some(current) = queue.pop() {
+            for supported in &self.supports[current] {
+                if !seen[*supported] {
+                    seen[*supported] = true;
+                    above.push(*supported);
+                    queue.push(*supported);
+                }
+            }
+        }
+
+        let Some(removed) = self.bricks[id].take() else {
+            return Vec::new();
+        };
+        let mut columns = vec![removed];
+        let mut falling: Vec<(BrickID, Brick)> = Vec::new();
+        for b in above.iter().copied().chain(iter::once(id)) {
+            for supporting in std::mem::take(&mut self.supported_by[b]) {
+                self.supports[supporting].retain(|s| *s != b);
+            }
+            self.supports[b].clear();
+            if let Some(brick) = self.bricks[b].take() {
+                columns.push(brick);
+                falling.push((b, brick));
+            }
+        }
+        falling.sort_by_key(|(_, ((s_z, _, _), _))| *s_z);
+
+        let mut moved = Vec::new();
+        for (b, brick) in falling {
+            self.fall(b, brick);
+            if self.bricks[b] != Some(brick) {
+                moved.push(b);
+            }
+        }
+        self.rebuild_heights(&columns);
+        moved.sort();
+        moved
+    }
+
+    /// Widens `heights` so the column (y, x) fits.
+    fn grow(&mut self, y: usize, x: usize) {
+        let height = self.heights.len().checked_div(self.width).unwrap_or(0);
+        if y < height && x < self.width {
+            return;
+        }
+        let (new_height, new_width) = (height.max(y + 1), self.width.max(x + 1));
+        let mut heights = vec![None; new_height * new_width];
+        for (i, column) in self.heights.iter().enumerate() {
+            heights[i / self.width * new_width + i % self.width] = *column;
+        }
+        self.heights = heights;
+        self.width = new_width;
+    }
+
+    fn drop_brick(&mut self, id: BrickID, brick: Brick) {
+        let ((_, s_y, s_x), (_, e_y, e_x)) = brick;
+        let width = self.width;
+        let footprint = || (s_y..=e_y).flat_map(move |y| (s_x..=e_x).map(move |x| y * width + x));
+        let top = footprint()
+            .filter_map(|cell| self.heights[cell])
+            .map(|(z, _)| z)
+            .max()
+            .unwrap_or(0);
+        let mut supported_by: Vec<BrickID> = footprint()
+            .filter_map(|cell| self.heights[cell])
+            .filter(|(z, _)| *z == top)
+            .map(|(_, brick)| brick)
+            .collect();
+        supported_by.sort();
+        supported_by.dedup();
+        self.place(id, brick, top, supported_by);
+        let new_top = self.bricks[id].unwrap().1 .0;
+        for cell in footprint() {
+            self.heights[cell] = Some((new_top, id));
+        }
+    }
+
+    /// Drops a brick that was lifted out of the stack. Bricks above it in its columns
+    /// are still there, so it lands on the highest top below its own bottom instead of
+    /// the column top.
+    fn fall(&mut self, id: BrickID, brick: Brick) {
+        let ((s_z, _, _), _) = brick;
+        let below: Vec<(BrickID, usize)> = self
+            .bricks()
+            .filter(|(_, other)| overlaps(&brick, other) && other.1 .0 < s_z)
+            .map(|(i, other)| (i, other.1 .0))
+            .collect();
+        let top = below.iter().map(|(_, z)| *z).max().unwrap_or(0);
+        let supported_by = below
+            .iter()
+            .filter(|(_, z)| *z == top)
+            .map(|(i, _)| *i)
+            .collect();
+        self.place(id, brick, top, supported_by);
+    }
+
+    /// Puts the brick right on top of `top` and links it with the bricks it rests on,
+    /// which have to be sorted.
+    fn place(&mut self, id: BrickID, brick: Brick, top: usize, supported_by: Vec<BrickID>) {
+        let ((s_z, s_y, s_x), (e_z, e_y, e_x)) = brick;
+        for supporting in supported_by.iter() {
+            self.supports[*supporting].push(id);
+        }
+        let new_z = top + 1;
+        self.supported_by[id] = supported_by;
+        self.bricks[id] = Some(((new_z, s_y, s_x), (new_z + e_z - s_z, e_y, e_x)));
+    }
+
+    /// Recomputes the column tops under the footprints of `columns` from the bricks left.
+    fn rebuild_heights(&mut self, columns: &[Brick]) {
+        let width = self.width;
+        let cells = |((_, s_y, s_x), (_, e_y, e_x)): Brick| {
+            (s_y..=e_y).flat_map(move |y| (s_x..=e_x).map(move |x| y * width + x))
+        };
+        let mut affected = vec![false; self.heights.len()];
+        for brick in columns {
+            for cell in cells(*brick) {
+                self.heights[cell] = None;
+                affected[cell] = true;
+            }
+        }
+        for (id, brick) in self.bricks.iter().enumerate() {
+            let Some(brick) = brick else { continue };
+            for cell in cells(*brick).filter(|cell| affected[*cell]) {
+                if self.heights[cell].is_none_or(|(z, _)| z < brick.1 .0) {
+                    self.heights[cell] = Some((brick.1 .0, id));
+                }
+            }
+        }
+    }
+
+    pub fn brick(&self, id: BrickID) -> Option<&Brick> {
+        self.bricks[id].as_ref()
+    }
+
+    /// All bricks that weren't removed, with their ids.
+    pub fn bricks(&self) -> impl Iterator<Item = (BrickID, &Brick)> {
+        self.bricks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| b.as_ref().map(|b| (i, b)))
+    }
+
+    /// Number of ids handed out, removed bricks included.
+    pub fn len(&self) -> usize {
+        self.bricks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bricks.is_empty()
+    }
+
+    /// Bricks right below this one, empty if it lies on the ground.
+    pub fn supported_by(&self, id: BrickID) -> &[BrickID] {
+        &self.supported_by[id]
+    }
+
+    /// Bricks right on top of this one.
+    pub fn supports(&self, id: BrickID) -> &[BrickID] {
+        &self.supports[id]
+    }
+}
+
+/// Whether the two bricks share a (y, x) column.
+fn overlaps(
+    ((_, a_s_y, a_s_x), (_, a_e_y, a_e_x)): &Brick,
+    ((_, b_s_y, b_s_x), (_, b_e_y, b_e_x)): &Brick,
+) -> bool {
+    a_s_y <= b_e_y && b_s_y <= a_e_y && a_s_x <= b_e_x && b_s_x <= a_e_x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    pub fn test_settle() {
+        let stack = BrickStack::settle(&parse(include_str!("test_input")));
+        let lowest_z: Vec<usize> = stack.bricks().map(|(_, b)| b.0 .0).collect();
+        assert_eq!(lowest_z, vec![1, 2, 2, 3, 3, 4, 5]);
+        assert_eq!(stack.supports(0), &[1, 2]);
+        assert_eq!(stack.supported_by(3), &[1, 2]);
+        assert_eq!(stack.supported_by(0), &[] as &[BrickID]);
+    }
+
+    #[test]
+    pub fn test_insert_and_remove() {
+        let mut stack = BrickStack::settle(&parse(include_str!("test_input")));
+        // A flat brick across the whole top lands on the vertical brick G
+        let lid = stack.insert(((100, 0, 0), (100, 2, 2)));
+        assert_eq!(stack.brick(lid), Some(&((7, 0, 0), (7, 2, 2))));
+        assert_eq!(stack.supported_by(lid), &[6]);
+        // Taking out B and C drops D and E to the ground, F then rests on A, D and E
+        assert!(stack.remove(1).is_empty());
+        assert_eq!(stack.remove(2), vec![3, 4, 5, 6, lid]);
+        assert_eq!(stack.supported_by(3), &[] as &[BrickID]);
+        assert_eq!(stack.supported_by(5), &[0, 3, 4]);
+        assert_eq!(stack.brick(lid), Some(&((5, 0, 0), (5, 2, 2))));
+        assert_eq!(stack.brick(2), None);
+        assert_eq!(stack.bricks().count(), stack.len() - 2);
+        assert!(!stack.is_empty() && BrickStack::new().is_empty());
+        // The column tops follow the bricks that fell
+        let cap = stack.insert(((100, 1, 1), (100, 1, 1)));
+        assert_eq!(stack.brick(cap), Some(&((6, 1, 1), (6, 1, 1))));
+        assert_eq!(stack.supported_by(cap), &[lid]);
+    }
+
+    #[test]
+    pub fn test_insert_outside_extent() {
+        let mut stack = BrickStack::settle(&parse(include_str!("test_input")));
+        let wide = stack.insert(((50, 2, 0), (50, 2, 5)));
+        assert_eq!(stack.brick(wide), Some(&((4, 2, 0), (4, 2, 5))));
+        assert_eq!(stack.supported_by(wide), &[3, 4]);
+        let far = stack.insert(((50, 9, 9), (51, 9, 9)));
+        assert_eq!(stack.brick(far), Some(&((1, 9, 9), (2, 9, 9))));
+        let next_to_far = stack.insert(((60, 8, 8), (60, 9, 8)));
+        assert_eq!(stack.brick(next_to_far), Some(&((1, 8, 8), (1, 9, 8))));
+        assert_eq!(stack.supported_by(next_to_far), &[] as &[BrickID]);
+    }
+
+    #[test]
+    pub fn test_remove_matches_settling_again() {
+        let bricks = parse(crate::MAIN_INPUT);
+        let stack = BrickStack::settle(&bricks);
+        for id in [0, 1, 17, 400, 1000, stack.len() - 1] {
+            let mut removed = stack.clone();
+            removed.remove(id);
+            let remaining: Vec<Brick> = removed.bricks().map(|(_, b)| *b).collect();
+            let settled = BrickStack::settle(&remaining);
+            let mut expected: Vec<Brick> = settled.bricks().map(|(_, b)| *b).collect();
+            let mut result = remaining;
+            expected.sort();
+            result.sort();
+            assert_eq!(result, expected, "{}", id);
+            for (i, _) in removed.bricks() {
+                for s in removed.supported_by(i) {
+                    assert!(removed.supports(*s).contains(&i));
+                }
+            }
+        }
+    }
+}
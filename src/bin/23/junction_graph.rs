@@ -0,0 +1,228 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+use fnv::FnvHashMap;
+
+use crate::{Graph, Solution};
+
+/// How many junctions deep the search is unrolled before the branches are handed to threads.
+const PARALLEL_DEPTH: usize = 6;
+
+/// The junction graph with junctions numbered `0..64`, so a set of visited junctions fits
+/// in a `u64`.
+#[derive(Debug, Clone)]
+pub struct JunctionGraph {
+    positions: Vec<(usize, usize)>,
+    edges: Vec<Vec<(usize, Solution)>>,
+    /// Longest edge into every junction, a path can't gain more by entering it.
+    longest_edges: Vec<Solution>,
+    start: usize,
+    end: usize,
+    /// The junction every path to the end has to come through, if there is only one.
+    /// Leaving it anywhere else means the end can never be reached.
+    last_junction: Option<(usize, Solution)>,
+}
+
+impl JunctionGraph {
+    pub fn new(graph: &Graph, start: (usize, usize), end: (usize, usize)) -> JunctionGraph {
+        let mut positions: Vec<(usize, usize)> = graph.keys().copied().collect();
+        positions.sort();
+        assert!(
+            positions.len() <= 64,
+            "{} junctions don't fit in a u64",
+            positions.len()
+        );
+        let indices: FnvHashMap<(usize, usize), usize> =
+            positions.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+        let edges: Vec<Vec<(usize, Solution)>> = positions
+            .iter()
+            .map(|p| graph[p].iter().map(|(n, l)| (indices[n], *l)).collect())
+            .collect();
+        let mut incoming: Vec<Vec<(usize, Solution)>> = vec![Vec::new(); edges.len()];
+        for (from, to_list) in edges.iter().enumerate() {
+            for (to, length) in to_list {
+                incoming[*to].push((from, *length));
+            }
+        }
+        let longest_edges = incoming
+            .iter()
+            .map(|e| e.iter().map(|(_, l)| *l).max().unwrap_or(0))
+            .collect();
+        let end = indices[&end];
+        let last_junction = match incoming[end][..] {
+            [(junction, length)] => Some((junction, length)),
+            _ => None,
+        };
+        JunctionGraph {
+            positions,
+            edges,
+            longest_edges,
+            start: indices[&start],
+            end,
+            last_junction,
+        }
+    }
+
+    /// Length of the longest path from start to end that visits no junction twice and the
+    /// junctions along it, `None` if the end can't be reached. With `parallel` the branches
+    /// a few junctions deep are searched on all cores.
+    pub fn longest_route(&self, parallel: bool) -> Option<(Solution, Vec<(usize, usize)>)> {
+        let best = AtomicI32::new(Solution::MIN);
+        let remaining: Solution = (0..self.edges.len())
+            .filter(|j| *j != self.start)
+            .map(|j| self.longest_edges[j])
+            .sum();
+        let start = (
+            (self.start, 1 << self.start, 0, remaining),
+            vec![self.start],
+        );
+        let found = if !parallel {
+            let (branch, mut path) = start;
+            let mut found = None;
+            self.search(branch, &mut path, &best, &mut found);
+            found
+        } else {
+            let mut branches = vec![start];
+            for _ in 0..PARALLEL_DEPTH {
+                branches = branches
+                    .into_iter()
+                    .flat_map(|branch| self.expand(branch))
+                    .collect();
+            }
+            let threads = thread::available_parallelism().map_or(1, |n| n.get());
+            let chunk_size = branches.len().div_ceil(threads).max(1);
+            thread::scope(|s| {
+                let handles: Vec<_> = branches
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let best = &best;
+                        s.spawn(move || {
+                            let mut found = None;
+                            for (branch, path) in chunk {
+                                self.search(*branch, &mut path.clone(), best, &mut found);
+                            }
+                            found
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .filter_map(|handle| handle.join().unwrap())
+                    .max_by_key(|(length, _)| *length)
+            })
+        };
+        found.map(|(length, path)| {
+            (
+                length,
+                path.into_iter().map(|j| self.positions[j]).collect(),
+            )
+        })
+    }
+
+    /// One step of the search without the pruning, the end and the last junction are kept
+    /// as they are.
+    fn expand(&self, (branch, path): (Branch, Vec<usize>)) -> Vec<(Branch, Vec<usize>)> {
+        let (junction, visited, length, remaining) = branch;
+        if junction == self.end || self.last_junction.is_some_and(|(j, _)| j == junction) {
+            return vec![(branch, path)];
+        }
+        self.edges[junction]
+            .iter()
+            .filter(|(next, _)| visited & (1 << next) == 0)
+            .map(|(next, edge)| {
+                let mut path = path.clone();
+                path.push(*next);
+                (
+                    (
+                        *next,
+                        visited | 1 << next,
+                        length + edge,
+                        remaining - self.longest_edges[*next],
+                    ),
+                    path,
+                )
+            })
+            .collect()
+    }
+
+    /// Depth first search that gives up on a branch once even taking the longest edge into
+    /// every unvisited junction can't beat the best path found so far. `path` holds the
+    /// junctions up to and including the current one.
+    fn search(
+        &self,
+        (junction, visited, length, remaining): Branch,
+        path: &mut Vec<usize>,
+        best: &AtomicI32,
+        found: &mut Found,
+    ) {
+        if junction == self.end {
+            record(length, path, best, found);
+            return;
+        }
+        if let Some((last_junction, edge)) = self.last_junction {
+            if junction == last_junction {
+                path.push(self.end);
+                record(length + edge, path, best, found);
+                path.pop();
+                return;
+            }
+        }
+        if length + remaining <= best.load(Ordering::Relaxed) {
+            return;
+        }
+        for (next, edge) in self.edges[junction].iter() {
+            if visited & (1 << next) != 0 {
+                continue;
+            }
+            path.push(*next);
+            self.search(
+                (
+                    *next,
+                    visited | 1 << next,
+                    length + edge,
+                    remaining - self.longest_edges[*next],
+                ),
+                path,
+                best,
+                found,
+            );
+            path.pop();
+        }
+    }
+}
+
+/// Keeps the path if it is the longest this thread has seen and shares its length with the
+/// other threads for pruning.
+fn record(length: Solution, path: &[usize], best: &AtomicI32, found: &mut Found) {
+    best.fetch_max(length, Ordering::Relaxed);
+    if found.as_ref().is_none_or(|(l, _)| length > *l) {
+        *found = Some((length, path.to_vec()));
+    }
+}
+
+/// Longest path a thread found, with the junctions along it.
+type Found = Option<(Solution, Vec<usize>)>;
+
+/// Current junction, visited junctions, path length so far and the sum of the longest edges
+/// of all unvisited junctions.
+type Branch = (usize, u64, Solution, Solution);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slopes::Slopes;
+    use crate::{parse, transform_grid};
+
+    #[test]
+    pub fn test_serial_and_parallel() {
+        let (grid, start, end) = parse(include_str!("test_input"));
+        let graph = transform_grid(&grid, start, end, &Slopes::climbable());
+        let junctions = JunctionGraph::new(&graph, (0, 1), (22, 21));
+        let serial = junctions.longest_route(false).unwrap();
+        let parallel = junctions.longest_route(true).unwrap();
+        assert_eq!(serial.0, 154);
+        assert_eq!(parallel.0, 154);
+        assert_eq!(serial.1.first(), Some(&(0, 1)));
+        assert_eq!(serial.1.last(), Some(&(22, 21)));
+    }
+}
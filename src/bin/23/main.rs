@@ -1,73 +1,40 @@
 #![feature(test)]
 
-use std::cmp::{max, Ordering};
-use std::collections::BinaryHeap;
-use std::hash::Hash;
+use std::env;
 use std::time::Instant;
 
+use aoc2023::debug;
 use fnv::{FnvHashMap, FnvHashSet};
 
+use crate::junction_graph::JunctionGraph;
+use crate::slopes::{Slopes, DIRECTIONS};
+
+mod junction_graph;
+mod slopes;
+
 type Solution = i32;
 type Grid = Vec<Vec<u8>>;
-pub type ParseOutput = (Grid, (usize, usize));
+/// The map, the start and the end.
+pub type ParseOutput = (Grid, (usize, usize), (usize, usize));
+/// Cells from the start to the end, both included.
+pub type Route = Vec<(usize, usize)>;
 
+/// Junctions with the length of the longest corridor to every junction they lead to.
 type Graph = FnvHashMap<(usize, usize), FnvHashMap<(usize, usize), Solution>>;
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Field(usize, usize, Solution, FnvHashSet<(usize, usize)>);
-
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct FieldN(
-    usize,
-    usize,
-    Solution,
-    FnvHashSet<(usize, usize)>,
-    Vec<(usize, usize)>,
-);
 const MAIN_INPUT: &str = include_str!("main_input");
 const TEST_INPUT: &str = include_str!("test_input");
 
-fn part_1((grid, start): &ParseOutput) -> Solution {
-    let mut current_steps: BinaryHeap<Field> = BinaryHeap::new();
-    current_steps.push(Field(start.0, start.1, 0, FnvHashSet::default()));
-    let mut max_path = Solution::MIN;
-    while let Some(Field(y, x, path_length, mut visited)) = current_steps.pop() {
-        visited.insert((y, x));
-        max_path = max(max_path, path_length);
-        for (n_y, n_x) in get_neighbours(grid, (y, x), &visited) {
-            current_steps.push(Field(n_y, n_x, path_length + 1, visited.clone()));
-        }
-    }
-    max_path
+fn part_1(parse_output: &ParseOutput) -> Solution {
+    let route = longest_route(parse_output, &Slopes::icy()).unwrap();
+    assert!(is_simple_path(&parse_output.0, &route));
+    (route.len() - 1) as Solution
 }
 
-fn part_2(out: &mut ParseOutput) -> Solution {
+fn part_2(parse_output: &ParseOutput) -> Solution {
     let before = Instant::now();
-    let graph = transform_grid(out);
-    let (grid, start) = out;
-    let end = (grid.len() - 1, grid.len() - 2);
-    let mut current_steps: BinaryHeap<Field> = BinaryHeap::new();
-    current_steps.push(Field(start.0, start.1, 0, FnvHashSet::default()));
-    let mut max_path = Solution::MIN;
-    while let Some(Field(y, x, path_length, mut visited)) = current_steps.pop() {
-        if visited.contains(&(y, x)) {
-            continue;
-        }
-        visited.insert((y, x));
-        if (y, x) == end {
-            max_path = max(max_path, path_length);
-            continue;
-        }
-
-        for (n_y, n_x, n_path_length) in get_neighbours_g(&graph, (y, x), &visited) {
-            current_steps.push(Field(
-                n_y,
-                n_x,
-                path_length + n_path_length,
-                visited.clone(),
-            ));
-        }
-    }
-    println!(
+    let route = longest_route(parse_output, &Slopes::climbable()).unwrap();
+    assert!(is_simple_path(&parse_output.0, &route));
+    debug!(
         "Part 2 took {}ms",
         Instant::now()
             .duration_since(before)
@@ -81,94 +48,178 @@ fn part_2(out: &mut ParseOutput) -> Solution {
             .unwrap()
             .join(",")
     );
-    max_path
+    (route.len() - 1) as Solution
 }
 
-fn transform_grid((grid, start): &mut ParseOutput) -> Graph {
+/// Longest route from start to end that never visits a cell twice, found on the junction
+/// graph and then walked out cell by cell.
+pub fn longest_route((grid, start, end): &ParseOutput, slopes: &Slopes) -> Option<Route> {
+    let graph = transform_grid(grid, *start, *end, slopes);
+    let (length, junctions) = JunctionGraph::new(&graph, *start, *end).longest_route(true)?;
+    let mut route = vec![junctions[0]];
+    for pair in junctions.windows(2) {
+        let edge_length = graph[&pair[0]][&pair[1]];
+        route.extend(corridor(
+            grid,
+            &graph,
+            slopes,
+            pair[0],
+            pair[1],
+            edge_length,
+        )?);
+    }
+    assert_eq!(route.len() - 1, length as usize);
+    Some(route)
+}
+
+/// Cells with more than two open neighbours, the start and the end become junctions. Every
+/// corridor that can be walked from one junction to the next under the slope rules is an
+/// edge, so with one-way tiles the graph is directed.
+fn transform_grid(
+    grid: &Grid,
+    start: (usize, usize),
+    end: (usize, usize),
+    slopes: &Slopes,
+) -> Graph {
     let mut graph = Graph::default();
-    let mut current_steps: BinaryHeap<Field> = BinaryHeap::new();
-    let mut visited: FnvHashSet<(usize, usize)> = FnvHashSet::default();
-    current_steps.push(Field(start.0, start.1, 0, FnvHashSet::default()));
-    graph.insert((start.0, start.1), FnvHashMap::default());
-    while let Some(Field(y, x, path_length, v)) = current_steps.pop() {
-        if visited.contains(&(y, x)) {
-            continue;
-        }
-        visited.insert((y, x));
-        let n = get_neighbours_climb(grid, (y, x), &v);a
-        if n.len() > 2 {
-            graph.insert((y, x), FnvHashMap::default());
-        }
-        for (n_y, n_x) in n.iter().filter(|na| !visited.contains(*na)) {
-            current_steps.push(Field(*n_y, *n_x, path_length + 1, v.clone()));
+    for (y, line) in grid.iter().enumerate() {
+        for x in 0..line.len() {
+            let open = grid[y][x] != b'#';
+            if open && get_neighbours(grid, (y, x), &Slopes::climbable()).count() > 2 {
+                graph.insert((y, x), FnvHashMap::default());
+            }
         }
     }
-    graph.insert((grid.len() - 1, grid.len() - 2), FnvHashMap::default());
-    visited.clear();
-    let mut edges_to_explore: Vec<((usize, usize), (usize, usize))> = Vec::new();
-    edges_to_explore.push((start.clone(), (start.0 + 1, start.1)));
-    visited.insert(*start);
-    while let Some((start_node_id, mut current)) = edges_to_explore.pop() {
-        let mut path_length = 1;
-        loop {
-            if graph.get_mut(&current).is_none() {
-                visited.insert(current);
-            }
-            if let Some(end_node) = graph.get_mut(&current) {
-                let n = get_neighbours(grid, current, &visited);
-                end_node.insert(start_node_id, path_length);
-                for e in n {
-                    edges_to_explore.push((current, e))
+    graph.insert(start, FnvHashMap::default());
+    graph.insert(end, FnvHashMap::default());
+    let junctions: Vec<(usize, usize)> = graph.keys().copied().collect();
+    for junction in junctions {
+        for first in get_neighbours(grid, junction, slopes) {
+            let (mut previous, mut current) = (junction, first);
+            let mut path_length = 1;
+            let reached = loop {
+                if graph.contains_key(&current) {
+                    break Some(current);
                 }
-                break;
-            }
-            let n = get_neighbours(grid, current, &visited);
-            current = match n.len() {
-                0 => panic!("opfpo9sfkaeopsik"),
-                1 => n[0],
-                2 => *n.iter().find(|p| !graph.contains_key(p)).unwrap(),
-                _ => panic!(),
+                let Some(next) = get_neighbours(grid, current, slopes).find(|n| *n != previous)
+                else {
+                    break None;
+                };
+                (previous, current) = (current, next);
+                path_length += 1;
             };
-            path_length += 1;
+            if let Some(reached) = reached.filter(|r| *r != junction) {
+                let edge = graph
+                    .get_mut(&junction)
+                    .unwrap()
+                    .entry(reached)
+                    .or_insert(0);
+                *edge = (*edge).max(path_length);
+            }
         }
-        assert!(graph.get_mut(&current).is_some());
-        let end_node = graph.get_mut(&start_node_id).unwrap();
-        end_node.insert(current, path_length);
     }
     graph
 }
 
+/// Cells of the corridor of the given length from one junction to another, without `from`.
+fn corridor(
+    grid: &Grid,
+    graph: &Graph,
+    slopes: &Slopes,
+    from: (usize, usize),
+    to: (usize, usize),
+    length: Solution,
+) -> Option<Route> {
+    get_neighbours(grid, from, slopes).find_map(|first| {
+        let (mut previous, mut current) = (from, first);
+        let mut cells = vec![first];
+        while !graph.contains_key(&current) {
+            let next = get_neighbours(grid, current, slopes).find(|n| *n != previous)?;
+            (previous, current) = (current, next);
+            cells.push(current);
+        }
+        (current == to && cells.len() == length as usize).then_some(cells)
+    })
+}
+
+/// Whether the route only steps between neighbouring open cells and never visits a cell
+/// twice.
+pub fn is_simple_path(grid: &Grid, route: &Route) -> bool {
+    let mut visited = FnvHashSet::default();
+    let open = |(y, x): (usize, usize)| {
+        grid.get(y)
+            .and_then(|l| l.get(x))
+            .is_some_and(|c| *c != b'#')
+    };
+    route
+        .iter()
+        .all(|cell| open(*cell) && visited.insert(*cell))
+        && route
+            .windows(2)
+            .all(|pair| manhattan_distance(pair[0], pair[1]) == 1)
+}
+
+/// The map with the route drawn as `O`.
+pub fn render(grid: &Grid, route: &Route) -> String {
+    let mut lines = grid.clone();
+    for (y, x) in route {
+        lines[*y][*x] = b'O';
+    }
+    lines
+        .iter()
+        .map(|l| String::from_utf8_lossy(l).into_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The start and end are the only gaps in the top and bottom row.
 pub fn parse(file: &'static str) -> ParseOutput {
     let grid: Grid = file
         .lines()
         .filter(|l| !l.is_empty())
         .map(|l| l.as_bytes().iter().map(|c| *c).collect())
         .collect();
-    (grid, (0, 1))
+    let gap = |y: usize| {
+        let gaps: Vec<usize> = (0..grid[y].len()).filter(|x| grid[y][*x] != b'#').collect();
+        match gaps[..] {
+            [x] => (y, x),
+            _ => panic!("Row {} needs exactly one gap, found {}", y, gaps.len()),
+        }
+    };
+    let (start, end) = (gap(0), gap(grid.len() - 1));
+    (grid, start, end)
 }
 
-fn get_neighbours_g(
-    graph: &Graph,
-    (y, x): (usize, usize),
-    visited: &FnvHashSet<(usize, usize)>,
-) -> Vec<(usize, usize, Solution)> {
-    graph
-        .get(&(y, x))
-        .unwrap()
-        .iter()
-        .filter_map(|((y, x), l)| {
-            if !visited.contains(&(*y, *x)) {
-                Some((*y, *x, *l))
-            } else {
-                None
-            }
-        })
-        .collect()
-}
 fn main() {
-    let parse_output = &mut parse(MAIN_INPUT);
+    aoc2023::trace::init();
+    let parse_output = &parse(MAIN_INPUT);
     println!("Solution to part 1 is {}", part_1(parse_output));
     println!("Solution to part 2 is {}", part_2(parse_output));
+    if env::args().any(|a| a == "--render") {
+        for slopes in [Slopes::icy(), Slopes::climbable()] {
+            if let Some(route) = longest_route(parse_output, &slopes) {
+                println!("\n{}", render(&parse_output.0, &route));
+            }
+        }
+    }
+}
+
+pub fn manhattan_distance(p1: (usize, usize), p2: (usize, usize)) -> Solution {
+    (p1.0.abs_diff(p2.0) + p1.1.abs_diff(p2.1)) as Solution
+}
+
+/// Cells next to `(y, x)` that can be stepped onto.
+fn get_neighbours<'a>(
+    grid: &'a Grid,
+    (y, x): (usize, usize),
+    slopes: &'a Slopes,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    DIRECTIONS.into_iter().filter_map(move |(d_y, d_x)| {
+        let n_y = y.checked_add_signed(d_y)?;
+        let n_x = x.checked_add_signed(d_x)?;
+        let tile = *grid.get(n_y)?.get(n_x)?;
+        slopes.can_enter(tile, (d_y, d_x)).then_some((n_y, n_x))
+    })
 }
 
 #[cfg(test)]
@@ -187,8 +238,55 @@ mod tests {
 
     #[test]
     pub fn test_part_2() {
-        let parse_output = &mut parse(TEST_INPUT);
-        assert_eq!(part_2(parse_output), 154);
+        let parse_output = parse(TEST_INPUT);
+        assert_eq!(part_2(&parse_output), 154);
+    }
+
+    #[test]
+    pub fn test_routes() {
+        let parse_output = parse(TEST_INPUT);
+        let route_1 = longest_route(&parse_output, &Slopes::icy()).unwrap();
+        let route_2 = longest_route(&parse_output, &Slopes::climbable()).unwrap();
+        for route in [&route_1, &route_2] {
+            assert!(is_simple_path(&parse_output.0, route));
+            assert_eq!(route.first(), Some(&(0, 1)));
+            assert_eq!(route.last(), Some(&(22, 21)));
+        }
+        let rendered = render(&parse_output.0, &route_1);
+        assert_eq!(rendered.matches('O').count(), 95);
+        assert!(rendered.starts_with("#O#####"));
+    }
+
+    #[test]
+    pub fn test_not_simple_path() {
+        let (grid, _, _) = parse(TEST_INPUT);
+        assert!(!is_simple_path(&grid, &vec![(0, 1), (1, 1), (0, 1)]));
+        assert!(!is_simple_path(&grid, &vec![(0, 1), (1, 2)]));
+        assert!(!is_simple_path(&grid, &vec![(0, 0), (0, 1)]));
+    }
+
+    #[test]
+    pub fn test_rectangular_map() {
+        let parse_output = parse(
+            "#######.#####
+#.......#...#
+#.###.#.#.#.#
+#.#...<...#.#
+#.#.###^#.#.#
+#.<.#...#...#
+#.#.#.###.###
+#.#...#.....#
+###########.#",
+        );
+        assert_eq!((parse_output.1, parse_output.2), ((0, 7), (8, 11)));
+        let route_1 = longest_route(&parse_output, &Slopes::icy()).unwrap();
+        let route_2 = longest_route(&parse_output, &Slopes::climbable()).unwrap();
+        assert_eq!((route_1.len() - 1, route_2.len() - 1), (36, 40));
+        assert!(is_simple_path(&parse_output.0, &route_1));
+        assert!(is_simple_path(&parse_output.0, &route_2));
+        // Up the ^ slope, but the < slopes would have to be climbed
+        assert!(route_1.contains(&(4, 7)));
+        assert!(!route_1.contains(&(3, 6)) && !route_1.contains(&(5, 2)));
     }
 
     #[bench]
@@ -202,127 +300,15 @@ mod tests {
     fn bench_part_1(b: &mut Bencher) {
         let parse_output = parse(MAIN_INPUT);
         b.iter(move || {
-            assert_eq!(part_1(black_box(&parse_output)), 1798691765);
+            assert_eq!(part_1(black_box(&parse_output)), 2406);
         });
     }
 
     #[bench]
     fn bench_part_2(b: &mut Bencher) {
-        let parse_output = &mut parse(MAIN_INPUT);
-        b.iter(|| {
-            assert_eq!(part_2(black_box(parse_output)), 1104);
+        let parse_output = parse(MAIN_INPUT);
+        b.iter(move || {
+            assert_eq!(part_2(black_box(&parse_output)), 6630);
         });
     }
 }
-
-pub fn manhattan_distance(p1: (usize, usize), p2: (usize, usize)) -> Solution {
-    (p1.0.abs_diff(p2.0) + p1.1.abs_diff(p2.1)) as Solution
-}
-
-fn get_neighbours(
-    grid: &Grid,
-    (y, x): (usize, usize),
-    visited: &FnvHashSet<(usize, usize)>,
-) -> Vec<(usize, usize)> {
-    let mut n = Vec::new();
-    let n_x = x.checked_sub(1);
-    if let Some(n_x) = n_x {
-        let c = grid[y][n_x];
-        if c == b'.' && !visited.contains(&(y, n_x)) {
-            n.push((y, n_x))
-        }
-    }
-    let n_x = x + 1;
-    if n_x < grid.len() {
-        let c = grid[y][n_x];
-        if (c == b'.' || c == b'>') && !visited.contains(&(y, n_x)) {
-            n.push((y, n_x))
-        }
-    }
-
-    let n_y = y.checked_sub(1);
-    if let Some(n_y) = n_y {
-        let c = grid[n_y][x];
-        if c == b'.' && !visited.contains(&(n_y, x)) {
-            n.push((n_y, x))
-        }
-    }
-
-    let n_y = y + 1;
-    if n_y < grid.len() {
-        let c = grid[n_y][x];
-        if (c == b'.' || c == b'v') && !visited.contains(&(n_y, x)) {
-            n.push((n_y, x))
-        }
-    }
-
-    n
-}
-
-fn get_neighbours_climb(
-    grid: &Grid,
-    (y, x): (usize, usize),
-    visited: &FnvHashSet<(usize, usize)>,
-) -> Vec<(usize, usize)> {
-    let mut n = Vec::new();
-    let n_x = x.checked_sub(1);
-    if let Some(n_x) = n_x {
-        let c = grid[y][n_x];
-        if c != b'#' && !visited.contains(&(y, n_x)) {
-            n.push((y, n_x))
-        }
-    }
-    let n_x = x + 1;
-    if n_x < grid.len() {
-        let c = grid[y][n_x];
-        if c != b'#' && !visited.contains(&(y, n_x)) {
-            n.push((y, n_x))
-        }
-    }
-
-    let n_y = y.checked_sub(1);
-    if let Some(n_y) = n_y {
-        let c = grid[n_y][x];
-        if c != b'#' && !visited.contains(&(n_y, x)) {
-            n.push((n_y, x))
-        }
-    }
-
-    let n_y = y + 1;
-    if n_y < grid.len() {
-        let c = grid[n_y][x];
-        if c != b'#' && !visited.contains(&(n_y, x)) {
-            n.push((n_y, x))
-        }
-    }
-
-    n
-}
-
-impl PartialOrd for Field {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.2 > other.2 {
-            return Some(Ordering::Less);
-        }
-
-        if self.2 == other.2 {
-            return Some(Ordering::Equal);
-        }
-
-        return Some(Ordering::Greater);
-    }
-}
-
-impl Ord for Field {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.2 > other.2 {
-            return Ordering::Less;
-        }
-
-        if self.2 == other.2 {
-            return Ordering::Equal;
-        }
-
-        return Ordering::Greater;
-    }
-}
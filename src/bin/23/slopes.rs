@@ -0,0 +1,62 @@
+/// (dy, dx) of a single step.
+pub type Direction = (isize, isize);
+
+/// Left, right, up, down.
+pub const DIRECTIONS: [Direction; 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// Tiles that can only be stepped onto moving in one direction. Everything but `#` is
+/// walkable, tiles without a rule in every direction.
+#[derive(Debug, Clone, Default)]
+pub struct Slopes {
+    one_way: Vec<(u8, Direction)>,
+}
+
+impl Slopes {
+    pub fn new(one_way: impl IntoIterator<Item = (u8, Direction)>) -> Slopes {
+        Slopes {
+            one_way: one_way.into_iter().collect(),
+        }
+    }
+
+    /// `^ > v <` can only be walked down the way they point.
+    pub fn icy() -> Slopes {
+        Slopes::new([
+            (b'^', (-1, 0)),
+            (b'>', (0, 1)),
+            (b'v', (1, 0)),
+            (b'<', (0, -1)),
+        ])
+    }
+
+    /// Slopes are walkable both ways.
+    pub fn climbable() -> Slopes {
+        Slopes::default()
+    }
+
+    pub fn can_enter(&self, tile: u8, direction: Direction) -> bool {
+        tile != b'#'
+            && self
+                .one_way
+                .iter()
+                .all(|(t, d)| *t != tile || *d == direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_can_enter() {
+        let icy = Slopes::icy();
+        assert!(icy.can_enter(b'<', (0, -1)));
+        assert!(!icy.can_enter(b'<', (0, 1)));
+        assert!(!icy.can_enter(b'^', (1, 0)));
+        assert!(icy.can_enter(b'.', (1, 0)));
+        assert!(!icy.can_enter(b'#', (1, 0)));
+        assert!(Slopes::climbable().can_enter(b'v', (-1, 0)));
+        let custom = Slopes::new([(b'=', (0, 1))]);
+        assert!(custom.can_enter(b'>', (0, -1)));
+        assert!(!custom.can_enter(b'=', (0, -1)));
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::f64;
 
+use aoc2023::debug;
 use nalgebra::Vector3;
 
 type Solution = f64;
@@ -85,7 +86,7 @@ fn part_2(projectiles: &mut ParseOutput) -> Solution {
                     intersect_3d(&try_projectiles[0], &try_projectiles[2]),
                 ) {
                     if i == i2 && i == i3 {
-                        println!("{:?}", i);
+                        debug!("{:?}", i);
                         intersection_point = i;
                         break 'outer;
                     }
@@ -136,6 +137,7 @@ fn intersect_3d(
 }
 
 fn main() {
+    aoc2023::trace::init();
     let parse_output = &mut parse(MAIN_INPUT);
     println!(
         "Solution to part 1 is {}",
@@ -1,5 +1,6 @@
 #![feature(test)]
 
+use aoc2023::trace;
 use fnv::{FnvHashMap, FnvHashSet};
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
@@ -61,18 +62,20 @@ pub fn parse(file: &'static str) -> ParseOutput {
         let (main_module_name, connected_modules) = l.split_once(": ").unwrap();
         if !module_name_to_id_map.contains_key(main_module_name) {
             module_name_to_id_map.insert(main_module_name, module_id);
-            println!(
+            trace!(
                 "{{\"caption\": \"{}\", \"id\": {}}},",
-                main_module_name, module_id
+                main_module_name,
+                module_id
             );
             nodes.push((main_module_name, Vec::new()));
             module_id += 1;
         }
         for module_name in connected_modules.split(" ") {
             if !module_name_to_id_map.contains_key(module_name) {
-                println!(
+                trace!(
                     "{{\"caption\": \"{}\", \"id\": {}}},",
-                    module_name, module_id
+                    module_name,
+                    module_id
                 );
                 module_name_to_id_map.insert(module_name, module_id);
                 nodes.push((module_name, Vec::new()));
@@ -85,9 +88,10 @@ pub fn parse(file: &'static str) -> ParseOutput {
         let main_node_id = module_name_to_id_map.get(module_name).unwrap();
         for module_name in connected_modules.split(" ") {
             let node_id = module_name_to_id_map.get(module_name).unwrap();
-            println!(
+            trace!(
                 "{{\"source\": \"{}\", \"target\": {}}},",
-                main_node_id, node_id
+                main_node_id,
+                node_id
             );
             let connected_node = nodes.get_mut(*node_id).unwrap();
             connected_node.1.push(*main_node_id);
@@ -103,6 +107,7 @@ pub fn parse(file: &'static str) -> ParseOutput {
     nodes
 }
 fn main() {
+    aoc2023::trace::init();
     let parse_output = &mut parse(MAIN_INPUT);
     let edges_to_remove = [(980, 294), (1020, 587), (479, 477)];
     println!(
@@ -0,0 +1,171 @@
+pub type BitRow = u128;
+
+/// One `u128` per row, bit `x` of a row is column `x`. Only grids up to 128 columns wide fit.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BitGrid {
+    rows: Vec<BitRow>,
+    width: usize,
+}
+
+impl BitGrid {
+    pub fn new(height: usize, width: usize) -> BitGrid {
+        assert!(
+            width <= BitRow::BITS as usize,
+            "BitGrid is at most 128 wide"
+        );
+        BitGrid {
+            rows: vec![0; height],
+            width,
+        }
+    }
+
+    /// Sets every cell that is `c` in the lines of `file`.
+    pub fn parse(file: &str, c: u8) -> BitGrid {
+        let lines: Vec<&[u8]> = file
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.as_bytes())
+            .collect();
+        let mut grid = BitGrid::new(lines.len(), lines.first().map_or(0, |l| l.len()));
+        for (y, l) in lines.iter().enumerate() {
+            for (x, character) in l.iter().enumerate() {
+                if *character == c {
+                    grid.set(y, x, true);
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn rows(&self) -> &[BitRow] {
+        &self.rows
+    }
+
+    pub fn rows_mut(&mut self) -> &mut [BitRow] {
+        &mut self.rows
+    }
+
+    /// All bits of a row that are inside the grid.
+    pub fn mask(&self) -> BitRow {
+        if self.width == BitRow::BITS as usize {
+            BitRow::MAX
+        } else {
+            (1 << self.width) - 1
+        }
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> bool {
+        self.rows[y] >> x & 1 == 1
+    }
+
+    pub fn set(&mut self, y: usize, x: usize, value: bool) {
+        if value {
+            self.rows[y] |= 1 << x;
+        } else {
+            self.rows[y] &= !(1 << x);
+        }
+    }
+
+    pub fn union_with(&mut self, other: &BitGrid) {
+        for (row, other_row) in self.rows.iter_mut().zip(other.rows.iter()) {
+            *row |= other_row;
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.rows.iter().map(|r| r.count_ones() as usize).sum()
+    }
+
+    /// Number of columns in which rows `a` and `b` differ.
+    pub fn row_difference(&self, a: usize, b: usize) -> u32 {
+        (self.rows[a] ^ self.rows[b]).count_ones()
+    }
+
+    /// Only visits set bits, so sparse grids transpose in O(set cells).
+    pub fn transpose(&self) -> BitGrid {
+        let mut transposed = BitGrid::new(self.width, self.height());
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in iter_bits(*row) {
+                transposed.rows[x] |= 1 << y;
+            }
+        }
+        transposed
+    }
+
+    pub fn to_string(&self, set: char, unset: char) -> String {
+        self.rows
+            .iter()
+            .map(|row| {
+                let line = (0..self.width)
+                    .map(|x| if row >> x & 1 == 1 { set } else { unset })
+                    .collect::<String>();
+                format!("{}\n", line)
+            })
+            .collect()
+    }
+}
+
+/// Indices of the set bits of `row`, lowest first.
+pub fn iter_bits(mut row: BitRow) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if row == 0 {
+            return None;
+        }
+        let x = row.trailing_zeros() as usize;
+        row &= row - 1;
+        Some(x)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_and_get() {
+        let grid = BitGrid::parse("#..\n.#.\n..#\n##.\n", b'#');
+        assert_eq!((grid.height(), grid.width()), (4, 3));
+        assert!(grid.get(0, 0) && grid.get(3, 1) && !grid.get(3, 2));
+        assert_eq!(grid.count_ones(), 5);
+        assert_eq!(grid.to_string('#', '.'), "#..\n.#.\n..#\n##.\n");
+    }
+
+    #[test]
+    pub fn test_transpose() {
+        let grid = BitGrid::parse("#..\n.#.\n..#\n##.\n", b'#');
+        let transposed = grid.transpose();
+        assert_eq!(transposed.to_string('#', '.'), "#..#\n.#.#\n..#.\n");
+        assert_eq!(transposed.transpose(), grid);
+    }
+
+    #[test]
+    pub fn test_row_difference() {
+        let grid = BitGrid::parse("#.#.\n#..#\n#.#.\n", b'#');
+        assert_eq!(grid.row_difference(0, 1), 2);
+        assert_eq!(grid.row_difference(0, 2), 0);
+        assert_eq!(
+            iter_bits(0b1010_0001).collect::<Vec<usize>>(),
+            vec![0, 5, 7]
+        );
+        let mut union = BitGrid::parse("#.\n..\n", b'#');
+        union.union_with(&BitGrid::parse("#.\n.#\n", b'#'));
+        assert_eq!(union.to_string('#', '.'), "#.\n.#\n");
+    }
+
+    #[test]
+    pub fn test_full_width() {
+        let mut grid = BitGrid::new(1, 128);
+        grid.set(0, 127, true);
+        assert_eq!(grid.mask(), BitRow::MAX);
+        assert!(grid.get(0, 127));
+        assert_eq!(grid.transpose().height(), 128);
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CycleResult<S> {
+    /// The state after `steps` applications of the step function.
+    pub state: S,
+    /// Index of the first state that is part of the cycle.
+    pub prefix_length: usize,
+    /// `None` if `steps` was reached before any state repeated.
+    pub cycle_length: Option<usize>,
+}
+
+/// Applies `step` to `start` `steps` times, but stops as soon as a state repeats and
+/// looks the answer up in the history instead. Every state is cloned once into the
+/// history, the lookup only keeps hashes and indices into it.
+pub fn state_after<S, F>(start: S, steps: usize, mut step: F) -> CycleResult<S>
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&mut S),
+{
+    let hash = |state: &S| {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        hasher.finish()
+    };
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    seen.entry(hash(&start)).or_default().push(0);
+    let mut state = start.clone();
+    let mut history: Vec<S> = vec![start];
+    for i in 1..=steps {
+        step(&mut state);
+        let state_hash = hash(&state);
+        let seen_at = seen
+            .get(&state_hash)
+            .and_then(|indices| indices.iter().find(|j| history[**j] == state));
+        if let Some(&cycle_start) = seen_at {
+            let cycle_length = i - cycle_start;
+            let index = cycle_start + (steps - cycle_start) % cycle_length;
+            return CycleResult {
+                state: history.swap_remove(index),
+                prefix_length: cycle_start,
+                cycle_length: Some(cycle_length),
+            };
+        }
+        seen.entry(state_hash).or_default().push(i);
+        history.push(state.clone());
+    }
+    CycleResult {
+        prefix_length: steps,
+        state,
+        cycle_length: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_state_after_with_cycle() {
+        // 0 1 2 3 4 2 3 4 ...
+        let step = |s: &mut u32| *s = if *s == 4 { 2 } else { *s + 1 };
+        let result = state_after(0, 1_000_000_000, step);
+        assert_eq!(result.prefix_length, 2);
+        assert_eq!(result.cycle_length, Some(3));
+        assert_eq!(result.state, 2 + (1_000_000_000 - 2) % 3);
+        for steps in 0..20 {
+            let mut expected = 0;
+            (0..steps).for_each(|_| step(&mut expected));
+            assert_eq!(state_after(0, steps, step).state, expected, "{}", steps);
+        }
+    }
+
+    #[test]
+    pub fn test_state_after_without_cycle() {
+        let result = state_after(0u64, 10, |s| *s += 3);
+        assert_eq!(result.state, 30);
+        assert_eq!(result.cycle_length, None);
+    }
+}
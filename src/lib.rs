@@ -0,0 +1,3 @@
+pub mod bit_grid;
+pub mod cycle;
+pub mod trace;
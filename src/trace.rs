@@ -0,0 +1,123 @@
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const ENV_VAR: &str = "AOC_LOG";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Level {
+    Off = 0,
+    Info = 1,
+    Debug = 2,
+    Trace = 3,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Level> {
+        match level.to_ascii_lowercase().as_str() {
+            "off" | "0" => Some(Level::Off),
+            "info" | "1" => Some(Level::Info),
+            "debug" | "2" => Some(Level::Debug),
+            "trace" | "3" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn from_u8(level: u8) -> Level {
+        match level {
+            0 => Level::Off,
+            1 => Level::Info,
+            2 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+}
+
+const UNINITIALIZED: u8 = u8::MAX;
+static LEVEL: AtomicU8 = AtomicU8::new(UNINITIALIZED);
+
+/// Reads the level from the command line, falling back to `AOC_LOG`.
+/// `-v` is debug, `-vv` is trace, `--log=<level>` sets any level.
+pub fn init() {
+    let mut level = None;
+    for arg in env::args().skip(1) {
+        level = match arg.as_str() {
+            "-v" => Some(Level::Debug),
+            "-vv" => Some(Level::Trace),
+            a => a.strip_prefix("--log=").and_then(Level::parse).or(level),
+        };
+    }
+    if let Some(level) = level {
+        set_level(level);
+    }
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Solvers are silent unless a level was set through `init`, `set_level` or `AOC_LOG`.
+pub fn level() -> Level {
+    let level = LEVEL.load(Ordering::Relaxed);
+    if level != UNINITIALIZED {
+        return Level::from_u8(level);
+    }
+    let from_env = env::var(ENV_VAR)
+        .ok()
+        .and_then(|l| Level::parse(&l))
+        .unwrap_or(Level::Off);
+    set_level(from_env);
+    from_env
+}
+
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && level <= self::level()
+}
+
+/// Messages go to stderr so the solutions on stdout stay clean.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::trace::enabled($crate::trace::Level::Info) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::trace::enabled($crate::trace::Level::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::trace::enabled($crate::trace::Level::Trace) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_level() {
+        assert_eq!(Level::parse("DEBUG"), Some(Level::Debug));
+        assert_eq!(Level::parse("3"), Some(Level::Trace));
+        assert_eq!(Level::parse("loud"), None);
+    }
+
+    #[test]
+    pub fn test_enabled() {
+        set_level(Level::Debug);
+        assert!(enabled(Level::Info) && enabled(Level::Debug));
+        assert!(!enabled(Level::Trace) && !enabled(Level::Off));
+        set_level(Level::Off);
+        assert!(!enabled(Level::Info));
+    }
+}